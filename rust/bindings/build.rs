@@ -1,5 +1,27 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+#[path = "build/assets.rs"]
+mod assets;
+#[path = "build/charmap.rs"]
+mod charmap;
+#[allow(dead_code)]
+#[path = "src/lz77.rs"]
+mod decoder;
+#[path = "build/huffman.rs"]
+mod huffman;
+#[path = "build/lz77.rs"]
+mod lz77;
+#[path = "build/rl.rs"]
+mod rl;
+
+/// The decoder is placed in IWRAM on the GBA, the build script runs it from anywhere
+macro_rules! iwram {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
+use iwram;
 
 fn main() {
     let output_path = PathBuf::from(env::var("OUT_DIR").unwrap());
@@ -8,6 +30,13 @@ fn main() {
         .join("../..")
         .canonicalize()
         .unwrap();
+
+    assets::build(&base_path.join("rust/graphics"), &output_path);
+    charmap::build(&base_path.join("charmap.txt"), &output_path);
+    generate_bindings(&base_path, &output_path);
+}
+
+fn generate_bindings(base_path: &Path, output_path: &Path) {
     let include_path = base_path.join("include");
     let include_path = include_path.to_str().unwrap();
     let builder = bindgen::Builder::default()
@@ -16,6 +45,8 @@ fn main() {
         .clang_args(["--target=arm-none-eabi", "-mthumb", "-march=armv4t"])
         .allowlist_file(".*/list_menu.h")
         .allowlist_file(".*/pokemon.h")
+        .allowlist_file(".*/pokemon_animation.h")
+        .allowlist_file(".*/pokemon_icon.h")
         .allowlist_file(".*/battle.h")
         .allowlist_file(".*/item.h")
         .allowlist_file(".*/item_icon.h")
@@ -33,6 +64,8 @@ fn main() {
         .allowlist_file(".*/menu_helpers.h")
         .allowlist_file(".*/bg.h")
         .allowlist_file(".*/sprite.h")
+        .allowlist_file(".*/sound.h")
+        .allowlist_file(".*/string_util.h")
         .allowlist_file(".*/palette.h")
         .allowlist_file(".*/decompress.h")
         .allowlist_file(".*/syscall.h")
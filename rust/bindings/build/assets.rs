@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::{decoder, huffman, lz77, rl};
+
+struct Asset {
+    data: Vec<u8>,
+    /// Pixel dimensions, for tile data converted from a png
+    dim: Option<(u32, u32)>,
+}
+
+struct Png {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    palette: Vec<[u8; 3]>,
+}
+
+fn read_png(path: &Path) -> Png {
+    let file = File::open(path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().expect("png header");
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).expect("png data");
+    if frame.color_type != png::ColorType::Indexed {
+        panic!("{}: only indexed pngs are supported", path.display());
+    }
+
+    let depth = frame.bit_depth as usize;
+    let pixels = (0..frame.height as usize)
+        .flat_map(|y| (0..frame.width as usize).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let bit = x * depth;
+            let byte = buf[y * frame.line_size + bit / 8];
+            let shift = 8 - depth - bit % 8;
+            (byte >> shift) & ((1 << depth) - 1) as u8
+        })
+        .collect();
+    let palette = reader
+        .info()
+        .palette
+        .as_ref()
+        .map(|plte| plte.as_chunks::<3>().0.to_vec())
+        .unwrap_or_default();
+
+    Png {
+        width: frame.width,
+        height: frame.height,
+        pixels,
+        palette,
+    }
+}
+
+fn to_tiles(path: &Path, png: &Png, bpp: u32) -> Vec<u8> {
+    if !png.width.is_multiple_of(8) || !png.height.is_multiple_of(8) {
+        panic!("{}: dimensions must be multiples of 8", path.display());
+    }
+    let pixel = |x: u32, y: u32| {
+        let index = png.pixels[(y * png.width + x) as usize];
+        if index as u32 >= 1 << bpp {
+            panic!(
+                "{}: color index {index} doesn't fit in {bpp}bpp",
+                path.display()
+            );
+        }
+        index
+    };
+
+    let mut data = Vec::with_capacity(png.pixels.len() * bpp as usize / 8);
+    for tile_y in 0..png.height / 8 {
+        for tile_x in 0..png.width / 8 {
+            for y in tile_y * 8..tile_y * 8 + 8 {
+                let row = tile_x * 8..tile_x * 8 + 8;
+                match bpp {
+                    4 => data.extend(row.step_by(2).map(|x| pixel(x, y) | pixel(x + 1, y) << 4)),
+                    _ => data.extend(row.map(|x| pixel(x, y))),
+                }
+            }
+        }
+    }
+    data
+}
+
+fn to_gbapal(colors: impl Iterator<Item = [u8; 3]>) -> Vec<u8> {
+    colors
+        .map(|[r, g, b]| (r as u16 >> 3) | (g as u16 >> 3) << 5 | (b as u16 >> 3) << 10)
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+fn read_jasc(path: &Path) -> Vec<[u8; 3]> {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("JASC-PAL") {
+        panic!("{}: not a JASC palette", path.display());
+    }
+    let count: usize = lines
+        .nth(1)
+        .and_then(|l| l.trim().parse().ok())
+        .expect("color count");
+    lines
+        .take(count)
+        .map(|line| {
+            let mut channels = line.split_whitespace().map(|c| c.parse().expect("color"));
+            [(); 3].map(|_| channels.next().expect("rgb triplet"))
+        })
+        .collect()
+}
+
+fn convert(graphics: &Path, output: &Path) -> Asset {
+    let source = graphics.join(output);
+    match output.extension().and_then(|ext| ext.to_str()) {
+        Some(ext @ ("4bpp" | "8bpp")) => {
+            let path = source.with_extension("png");
+            let png = read_png(&path);
+            let bpp = match ext {
+                "4bpp" => 4,
+                _ => 8,
+            };
+            Asset {
+                data: to_tiles(&path, &png, bpp),
+                dim: Some((png.width, png.height)),
+            }
+        }
+        Some("gbapal") => {
+            let pal = source.with_extension("pal");
+            let colors = match pal.exists() {
+                true => read_jasc(&pal),
+                false => read_png(&source.with_extension("png")).palette,
+            };
+            Asset {
+                data: to_gbapal(colors.into_iter()),
+                dim: None,
+            }
+        }
+        _ => Asset {
+            data: fs::read(&source).unwrap_or_else(|err| panic!("{}: {err}", source.display())),
+            dim: None,
+        },
+    }
+}
+
+fn concat(graphics: &Path, output: &Path, parts: &str) -> Asset {
+    let dir = output.parent().unwrap_or(Path::new(""));
+    let parts: Vec<Asset> = parts
+        .split_whitespace()
+        .map(|part| convert(graphics, &dir.join(part)))
+        .collect();
+
+    // Sheets stacked vertically keep their dimensions if they share a width
+    let dim = parts
+        .iter()
+        .map(|part| part.dim)
+        .try_fold(None, |acc, dim| match (acc, dim?) {
+            (None, dim) => Some(Some(dim)),
+            (Some((w, h)), (width, height)) if w == width => Some(Some((w, h + height))),
+            _ => None,
+        });
+    Asset {
+        data: parts.into_iter().flat_map(|part| part.data).collect(),
+        dim: dim.flatten(),
+    }
+}
+
+fn ident(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Module {
+    items: Vec<String>,
+    children: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn insert(&mut self, path: &Path, item: String) {
+        let module = path
+            .parent()
+            .into_iter()
+            .flat_map(Path::iter)
+            .fold(self, |module, dir| {
+                let name = ident(&dir.to_string_lossy()).to_lowercase();
+                module.children.entry(name).or_default()
+            });
+        module.items.push(item);
+    }
+
+    fn write(&self, code: &mut String) {
+        for item in &self.items {
+            code.push_str(item);
+        }
+        for (name, module) in &self.children {
+            _ = writeln!(code, "pub mod {name} {{");
+            _ = writeln!(code, "#[allow(unused_imports)] use crate::graphics::*;");
+            _ = writeln!(code, "#[allow(unused_imports)] use crate::resources::*;");
+            module.write(code);
+            _ = writeln!(code, "}}");
+        }
+    }
+}
+
+enum Kind {
+    Image,
+    Tilemap,
+    Data,
+}
+
+/// Element type and kind of resource, from the output's extensions
+fn element_type(output: &Path) -> (&'static str, Kind) {
+    let name = output.file_name().unwrap().to_string_lossy();
+    let ext = |suffix: &str| name.ends_with(suffix);
+    match () {
+        _ if ext(".4bpp") => ("TileBitmap4bpp", Kind::Image),
+        _ if ext(".8bpp") => ("TileBitmap8bpp", Kind::Image),
+        _ if ext(".gbapal") => ("u16", Kind::Data),
+        _ if ext(".plain.bin") => ("TilePlain", Kind::Tilemap),
+        _ if ext(".affine.bin") => ("TileAffine", Kind::Tilemap),
+        _ if ext(".bin") => ("Tile4bpp", Kind::Tilemap),
+        _ => ("u8", Kind::Data),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Compression {
+    Lz77,
+    RunLength,
+    Huffman(u8),
+}
+
+impl Compression {
+    fn parse(option: &str) -> Option<Self> {
+        match option {
+            "lz77" => Some(Compression::Lz77),
+            "rl" => Some(Compression::RunLength),
+            "huff4" => Some(Compression::Huffman(4)),
+            "huff8" => Some(Compression::Huffman(8)),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::Lz77 => "lz",
+            Compression::RunLength => "rl",
+            Compression::Huffman(_) => "huff",
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::Lz77 => {
+                let compressed = lz77::compress(data);
+                check_decoder(&compressed, data);
+                compressed
+            }
+            Compression::RunLength => rl::compress(data),
+            Compression::Huffman(bits) => huffman::compress(data, bits),
+        }
+    }
+}
+
+/// Runs the runtime LZ77 decoder in small steps, to catch regressions at build time
+fn check_decoder(compressed: &[u8], data: &[u8]) {
+    let mut check = vec![0; data.len()];
+    let mut decoder = unsafe { decoder::Lz77Decoder::new(compressed.as_ptr()) };
+    while !decoder.decode(&mut check, 7) {}
+    assert!(check == data, "LZ77 decoder output differs from the source");
+}
+
+fn parse_dim(output: &Path, dim: &str) -> (usize, usize) {
+    dim.split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .unwrap_or_else(|| panic!("{}: invalid option {dim:?}", output.display()))
+}
+
+/// Converts and compresses every asset listed in `graphics/assets.txt`,
+/// generating `assets.rs` with a resource static for each of them
+pub fn build(graphics: &Path, output_path: &Path) {
+    let manifest = graphics.join("assets.txt");
+    println!("cargo:rerun-if-changed={}", graphics.display());
+    let text = fs::read_to_string(&manifest).expect("assets manifest");
+
+    let mut root = Module::default();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (head, parts) = match line.split_once(':') {
+            Some((head, parts)) => (head, Some(parts)),
+            None => (line, None),
+        };
+        let mut head = head.split_whitespace();
+        let output = PathBuf::from(head.next().unwrap());
+        let mut dim = None;
+        let mut compression = Compression::Lz77;
+        for option in head {
+            match Compression::parse(option) {
+                Some(format) => compression = format,
+                None => dim = Some(parse_dim(&output, option)),
+            }
+        }
+        let asset = match parts {
+            Some(parts) => concat(graphics, &output, parts),
+            None => convert(graphics, &output),
+        };
+
+        let compressed_path = output_path.join("graphics").join(format!(
+            "{}.{}",
+            output.display(),
+            compression.extension()
+        ));
+        fs::create_dir_all(compressed_path.parent().unwrap()).expect("asset output dir");
+        fs::write(&compressed_path, compression.compress(&asset.data)).expect("asset output");
+
+        let name = ident(&output.file_name().unwrap().to_string_lossy()).to_uppercase();
+        let size = asset.data.len();
+        let bytes = format!("include_bytes!({compressed_path:?})");
+        let (ty, kind) = element_type(&output);
+        let item = match (kind, asset.dim, dim) {
+            (Kind::Image, Some((width, height)), None) => format!(
+                "pub static {name}: ImageResource<{ty}, {size}> = \
+                 ImageResource::new({width}, {height}, CompressedResource::from_ref({bytes}));\n"
+            ),
+            (Kind::Tilemap, _, Some((width, height))) => {
+                let tile_size = match ty {
+                    "Tile4bpp" => 2,
+                    _ => 1,
+                };
+                if width * height * tile_size != size {
+                    panic!(
+                        "{}: {width}x{height} tiles don't match its {size} bytes",
+                        output.display()
+                    );
+                }
+                format!(
+                    "pub static {name}: TilemapResource<{ty}, {width}, {height}> = \
+                     TilemapResource::from_ref({bytes});\n"
+                )
+            }
+            (Kind::Tilemap, _, None) => {
+                panic!("{}: tilemaps need their dimensions", output.display())
+            }
+            (_, _, Some(_)) => panic!("{}: unexpected dimensions", output.display()),
+            _ => format!(
+                "pub static {name}: CompressedResource<{ty}, {size}> = \
+                 CompressedResource::from_ref({bytes});\n"
+            ),
+        };
+        root.insert(&output, item);
+    }
+
+    let mut code = String::new();
+    root.write(&mut code);
+    fs::write(output_path.join("assets.rs"), code).expect("Writing assets");
+}
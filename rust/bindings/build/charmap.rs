@@ -0,0 +1,157 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+pub struct Charmap {
+    /// Chars in file order, the first definition of a char wins
+    pub chars: Vec<(char, Vec<u8>)>,
+    /// `{NAME}` codes in file order, the first definition of a name wins
+    pub names: Vec<(String, Vec<u8>)>,
+}
+
+/// Splits off the `@` comment, outside of a quoted char
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, char) in line.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            '@' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_char(key: &str) -> Option<char> {
+    let inner = key.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = inner.chars();
+    let char = match chars.next()? {
+        '\\' => match chars.next()? {
+            'n' => '\n',
+            '\'' => '\'',
+            '\\' => '\\',
+            // `\l` and `\p` have no char, they are the `{L}` and `{P}` codes
+            _ => return None,
+        },
+        char => char,
+    };
+    chars.next().is_none().then_some(char)
+}
+
+pub fn parse(path: &Path) -> Charmap {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let mut charmap = Charmap {
+        chars: Vec::new(),
+        names: Vec::new(),
+    };
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.rsplit_once('=') else {
+            panic!("{}:{}: expected `key = bytes`", path.display(), number + 1);
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let bytes: Vec<u8> = value
+            .split_whitespace()
+            .map(|byte| {
+                u8::from_str_radix(byte, 16).unwrap_or_else(|_| {
+                    panic!("{}:{}: bad byte {byte:?}", path.display(), number + 1)
+                })
+            })
+            .collect();
+
+        match key.starts_with('\'') {
+            true => match parse_char(key) {
+                Some(char) if !charmap.chars.iter().any(|(c, _)| *c == char) => {
+                    charmap.chars.push((char, bytes))
+                }
+                Some(_) => {}
+                None if key == "'\\l'" => charmap.names.push(("L".into(), bytes)),
+                None if key == "'\\p'" => charmap.names.push(("P".into(), bytes)),
+                None => panic!("{}:{}: bad char {key}", path.display(), number + 1),
+            },
+            false if charmap.names.iter().any(|(name, _)| name == key) => {}
+            false => charmap.names.push((key.to_string(), bytes)),
+        }
+    }
+    charmap
+}
+
+fn bytes_pattern(bytes: &[u8]) -> String {
+    let bytes: Vec<_> = bytes.iter().map(|byte| format!("{byte:#04X}")).collect();
+    format!("[{}]", bytes.join(", "))
+}
+
+fn bytes_literal(bytes: &[u8]) -> String {
+    format!("&{}", bytes_pattern(bytes))
+}
+
+/// Writes the encode and decode lookups for the `charmap` module
+pub fn build(path: &Path, output_path: &Path) {
+    println!("cargo:rerun-if-changed={}", path.display());
+    let charmap = parse(path);
+
+    let mut out = String::new();
+    writeln!(out, "/// Bytes of a char in `charmap.txt`").unwrap();
+    writeln!(
+        out,
+        "pub const fn encode_char(char: char) -> Option<&'static [u8]> {{"
+    )
+    .unwrap();
+    writeln!(out, "    match char {{").unwrap();
+    for (char, bytes) in &charmap.chars {
+        writeln!(out, "        {char:?} => Some({}),", bytes_literal(bytes)).unwrap();
+    }
+    writeln!(out, "        _ => None,\n    }}\n}}\n").unwrap();
+
+    writeln!(out, "/// Bytes of a `{{NAME}}` code in `charmap.txt`").unwrap();
+    writeln!(
+        out,
+        "pub const fn encode_name(name: &[u8]) -> Option<&'static [u8]> {{"
+    )
+    .unwrap();
+    writeln!(out, "    match name {{").unwrap();
+    for (name, bytes) in &charmap.names {
+        writeln!(out, "        b{name:?} => Some({}),", bytes_literal(bytes)).unwrap();
+    }
+    writeln!(out, "        _ => None,\n    }}\n}}\n").unwrap();
+
+    // The first char or name of some bytes is the one they decode to, so
+    // latin letters win over the kana sharing their bytes
+    writeln!(out, "/// Char encoded as a single byte in `charmap.txt`").unwrap();
+    writeln!(out, "pub const fn decode_char(byte: u8) -> Option<char> {{").unwrap();
+    writeln!(out, "    match byte {{").unwrap();
+    let mut decoded = [false; 256];
+    for (char, bytes) in &charmap.chars {
+        if let [byte] = bytes[..]
+            && !decoded[byte as usize]
+        {
+            decoded[byte as usize] = true;
+            writeln!(out, "        {byte:#04X} => Some({char:?}),").unwrap();
+        }
+    }
+    writeln!(out, "        _ => None,\n    }}\n}}\n").unwrap();
+
+    writeln!(out, "/// Name of a `{{NAME}}` code in `charmap.txt`").unwrap();
+    writeln!(
+        out,
+        "pub const fn decode_name(bytes: &[u8]) -> Option<&'static str> {{"
+    )
+    .unwrap();
+    writeln!(out, "    match bytes {{").unwrap();
+    let mut decoded: Vec<&[u8]> = Vec::new();
+    for (name, bytes) in &charmap.names {
+        if !decoded.contains(&&bytes[..]) {
+            decoded.push(bytes);
+            writeln!(out, "        {} => Some({name:?}),", bytes_pattern(bytes)).unwrap();
+        }
+    }
+    writeln!(out, "        _ => None,\n    }}\n}}").unwrap();
+
+    fs::write(output_path.join("charmap.rs"), out).unwrap();
+}
@@ -0,0 +1,169 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+enum Node {
+    Leaf(u8),
+    Branch(usize, usize),
+}
+
+/// Symbols of `bits` width in decoding order, low nibble first for 4 bits
+fn symbols(data: &[u8], bits: u8) -> Vec<u8> {
+    match bits {
+        4 => data.iter().flat_map(|&b| [b & 0xF, b >> 4]).collect(),
+        _ => data.to_vec(),
+    }
+}
+
+/// Addresses in the tree are relative to the header, which is 5 bytes before the table
+const TABLE_OFFSET: usize = 5;
+/// Largest distance in pairs from a branch to its children, minus one
+const MAX_CHILD_OFFSET: usize = 0x3F;
+
+/// Index of the pair of nodes holding `slot`, counted from the header
+fn pair(slot: usize) -> usize {
+    (slot + TABLE_OFFSET) / 2
+}
+
+/// Number of branches under and including `node`
+fn branches(nodes: &[Node], node: usize) -> usize {
+    match nodes[node] {
+        Node::Leaf(_) => 0,
+        Node::Branch(node0, node1) => 1 + branches(nodes, node0) + branches(nodes, node1),
+    }
+}
+
+/// Lays out the tree with both children of a branch next to each other, at
+/// most 0x40 pairs after it
+///
+/// Breadth first runs out of range on wide trees, as each branch of a level
+/// waits for all the others. Here the smaller subtree of a branch is laid out
+/// first, depth first, so few branches wait at once and not for long. Branches
+/// left waiting are placed as soon as their deadlines leave no slack.
+fn tree_table(nodes: &[Node], root: usize) -> Vec<u8> {
+    let deadline = |slot: usize| pair(slot) + 1 + MAX_CHILD_OFFSET;
+    let mut table = vec![0u8];
+    // Branches already in the table with their children still to place, next one last
+    let mut pending = vec![(root, 0usize)];
+    while !pending.is_empty() {
+        let child = table.len();
+        let mut deadlines: Vec<(usize, usize)> = pending
+            .iter()
+            .enumerate()
+            .map(|(index, &(_, slot))| (deadline(slot), index))
+            .collect();
+        deadlines.sort();
+        if deadlines[0].0 < pair(child) {
+            panic!("Huffman tree too wide to encode");
+        }
+        let tight = deadlines
+            .iter()
+            .enumerate()
+            .any(|(waiting, &(deadline, _))| deadline <= pair(child) + waiting);
+        let (node, slot) = match tight {
+            true => pending.remove(deadlines[0].1),
+            false => pending.pop().unwrap(),
+        };
+
+        let Node::Branch(node0, node1) = nodes[node] else {
+            unreachable!("Only branches have children");
+        };
+        table.extend([0, 0]);
+        let offset = pair(child) - pair(slot) - 1;
+        let leaves = [node0, node1].map(|n| matches!(nodes[n], Node::Leaf(_)));
+        table[slot] = offset as u8 | (leaves[0] as u8) << 7 | (leaves[1] as u8) << 6;
+
+        let mut children = [(node0, child), (node1, child + 1)];
+        children.sort_by_key(|&(node, _)| Reverse(branches(nodes, node)));
+        for (node, slot) in children {
+            match nodes[node] {
+                Node::Leaf(symbol) => table[slot] = symbol,
+                Node::Branch(..) => pending.push((node, slot)),
+            }
+        }
+    }
+    table
+}
+
+/// Compresses `data` in the BIOS Huffman format (type 0x20) with 4 or 8 bit symbols
+///
+/// The BIOS writes whole words, so `data` must be a multiple of 4 bytes.
+pub fn compress(data: &[u8], bits: u8) -> Vec<u8> {
+    assert!(bits == 4 || bits == 8, "Huffman symbols are 4 or 8 bits");
+    assert!(
+        data.len().is_multiple_of(4),
+        "Huffman data must be word aligned"
+    );
+
+    let symbols = symbols(data, bits);
+    let mut freqs = vec![0usize; 1 << bits];
+    for &symbol in &symbols {
+        freqs[symbol as usize] += 1;
+    }
+
+    // The decoder needs a branch at the root, so there are always two leaves
+    let mut used: Vec<u8> = (0..1 << bits)
+        .filter(|&s| freqs[s] > 0)
+        .map(|s| s as u8)
+        .collect();
+    while used.len() < 2 {
+        let unused = (0..=u8::MAX).find(|s| !used.contains(s)).unwrap();
+        used.push(unused);
+    }
+
+    let mut nodes: Vec<Node> = used.iter().map(|&s| Node::Leaf(s)).collect();
+    let mut heap: BinaryHeap<_> = used
+        .iter()
+        .enumerate()
+        .map(|(index, &s)| Reverse((freqs[s as usize], index)))
+        .collect();
+    while heap.len() > 1 {
+        let Reverse((freq0, node0)) = heap.pop().unwrap();
+        let Reverse((freq1, node1)) = heap.pop().unwrap();
+        nodes.push(Node::Branch(node0, node1));
+        heap.push(Reverse((freq0 + freq1, nodes.len() - 1)));
+    }
+    let root = nodes.len() - 1;
+
+    let mut codes = vec![(0u32, 0u8); 1 << bits];
+    let mut stack = vec![(root, 0u32, 0u8)];
+    while let Some((node, code, len)) = stack.pop() {
+        match nodes[node] {
+            Node::Leaf(symbol) => codes[symbol as usize] = (code, len),
+            Node::Branch(node0, node1) => {
+                stack.push((node0, code << 1, len + 1));
+                stack.push((node1, code << 1 | 1, len + 1));
+            }
+        }
+    }
+
+    let mut table = tree_table(&nodes, root);
+
+    let mut out = Vec::with_capacity(data.len() + table.len() + 8);
+    out.extend_from_slice(&((0x20 | bits as u32) | (data.len() as u32) << 8).to_le_bytes());
+    // The size byte counts itself, in units of 2 bytes minus one
+    while !(table.len() + 1).is_multiple_of(4) {
+        table.push(0);
+    }
+    let table_size = table.len() + 1;
+    out.push((table_size / 2 - 1) as u8);
+    out.extend_from_slice(&table);
+
+    let mut word = 0u32;
+    let mut word_bits = 0;
+    for symbol in symbols {
+        let (code, len) = codes[symbol as usize];
+        for bit in (0..len).rev() {
+            word |= (code >> bit & 1) << (31 - word_bits);
+            word_bits += 1;
+            if word_bits == 32 {
+                out.extend_from_slice(&word.to_le_bytes());
+                word = 0;
+                word_bits = 0;
+            }
+        }
+    }
+    if word_bits != 0 {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    out
+}
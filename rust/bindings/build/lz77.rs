@@ -0,0 +1,59 @@
+/// Compresses `data` in the BIOS LZ77 format (type 0x10)
+///
+/// Matches never reference the byte right before the cursor, so the output
+/// can also be decompressed straight to vram with `LZ77UnCompVram`.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 0x1000;
+    const MIN_DISTANCE: usize = 2;
+    const MIN_LEN: usize = 3;
+    const MAX_LEN: usize = 18;
+
+    let mut out = Vec::with_capacity(data.len() / 2 + 8);
+    out.extend_from_slice(&(0x10 | (data.len() as u32) << 8).to_le_bytes());
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let flag_index = out.len();
+        out.push(0);
+        for bit in 0..8 {
+            if pos >= data.len() {
+                break;
+            }
+
+            let max_len = MAX_LEN.min(data.len() - pos);
+            let mut best = (0, 0);
+            for distance in MIN_DISTANCE..=WINDOW.min(pos) {
+                let start = pos - distance;
+                let len = (0..max_len)
+                    .take_while(|&i| data[start + i] == data[pos + i])
+                    .count();
+                if len > best.0 {
+                    best = (len, distance);
+                    if len == max_len {
+                        break;
+                    }
+                }
+            }
+
+            match best {
+                (len, distance) if len >= MIN_LEN => {
+                    out[flag_index] |= 0x80 >> bit;
+                    let len_bits = (len - MIN_LEN) << 4;
+                    let distance = distance - 1;
+                    out.push((len_bits | distance >> 8) as u8);
+                    out.push(distance as u8);
+                    pos += len;
+                }
+                _ => {
+                    out.push(data[pos]);
+                    pos += 1;
+                }
+            }
+        }
+    }
+
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+    out
+}
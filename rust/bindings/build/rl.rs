@@ -0,0 +1,42 @@
+/// Compresses `data` in the BIOS run-length format (type 0x30)
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    const MIN_RUN: usize = 3;
+    const MAX_RUN: usize = 0x7F + MIN_RUN;
+    const MAX_RAW: usize = 0x7F + 1;
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_RAW + 8);
+    out.extend_from_slice(&(0x30 | (data.len() as u32) << 8).to_le_bytes());
+
+    let run_at = |pos: usize| {
+        let byte = data[pos];
+        data[pos..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == byte)
+            .count()
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let start = pos;
+        while pos < data.len() && pos - start < MAX_RAW && run_at(pos) < MIN_RUN {
+            pos += 1;
+        }
+        if pos > start {
+            out.push((pos - start - 1) as u8);
+            out.extend_from_slice(&data[start..pos]);
+        }
+
+        if pos < data.len() && run_at(pos) >= MIN_RUN {
+            let len = run_at(pos);
+            out.push(0x80 | (len - MIN_RUN) as u8);
+            out.push(data[pos]);
+            pos += len;
+        }
+    }
+
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+    out
+}
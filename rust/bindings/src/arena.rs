@@ -0,0 +1,141 @@
+use alloc::boxed::Box;
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use crate::heap::{OutOfMemory, try_alloc_bytes};
+use crate::resources::{Buffer, CompressedResource};
+
+/// Bump allocator over one `gHeap` block, freed all at once when dropped
+///
+/// A scene can allocate its buffers here instead of scattering them over
+/// `gHeap`, so the C code running after it finds the heap unfragmented.
+pub struct Arena {
+    block: NonNull<u8>,
+    size: usize,
+    used: Cell<usize>,
+}
+
+impl Arena {
+    pub fn new(size: usize) -> Result<Self, OutOfMemory> {
+        let block = Box::into_raw(try_alloc_bytes(size)?);
+        Ok(Arena {
+            block: unsafe { NonNull::new_unchecked(block.cast()) },
+            size,
+            used: Cell::new(0),
+        })
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.size - self.used.get()
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> Result<NonNull<u8>, OutOfMemory> {
+        let base = self.block.as_ptr() as usize;
+        let start = (base + self.used.get()).next_multiple_of(layout.align()) - base;
+        let end = start + layout.size();
+        if end > self.size {
+            return Err(OutOfMemory::Heap(layout.size()));
+        }
+        self.used.set(end);
+        Ok(unsafe { self.block.add(start) })
+    }
+
+    /// Zeroed buffer of `size` bytes, viewed as `T`s like [`crate::resources::AllocBuf`]
+    pub fn alloc_buf<T>(&self, size: usize) -> Result<ArenaBuf<'_, T>, OutOfMemory> {
+        let layout = Layout::from_size_align(size, align_of::<T>()).unwrap();
+        let data = self.alloc_layout(layout)?;
+        unsafe { data.write_bytes(0, size) };
+        Ok(ArenaBuf {
+            data,
+            size,
+            _p: PhantomData,
+        })
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        let block = ptr::slice_from_raw_parts_mut(self.block.as_ptr(), self.size);
+        drop(unsafe { Box::from_raw(block) });
+    }
+}
+
+/// Value living in an [`Arena`], dropped in place but only freed with the arena
+pub struct ArenaBox<'a, T> {
+    value: NonNull<T>,
+    _p: PhantomData<(&'a Arena, T)>,
+}
+
+impl<'a, T> ArenaBox<'a, T> {
+    pub fn new_in(value: T, arena: &'a Arena) -> Result<Self, OutOfMemory> {
+        let ptr = arena.alloc_layout(Layout::new::<T>())?.cast::<T>();
+        unsafe { ptr.write(value) };
+        Ok(ArenaBox {
+            value: ptr,
+            _p: PhantomData,
+        })
+    }
+}
+
+impl<T> Deref for ArenaBox<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for ArenaBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T> Drop for ArenaBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.value.drop_in_place() };
+    }
+}
+
+pub struct ArenaBuf<'a, T> {
+    data: NonNull<u8>,
+    size: usize,
+    _p: PhantomData<(&'a Arena, T)>,
+}
+
+impl<T> ArenaBuf<'_, T> {
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.data.as_ptr().cast()
+    }
+}
+
+impl<T> Buffer<T> for ArenaBuf<'_, T> {
+    fn get(&self) -> impl Deref<Target = [T]> {
+        let bytes = unsafe { core::slice::from_raw_parts(self.data.as_ptr(), self.size) };
+        unsafe { bytes.align_to().1 }
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.size
+    }
+}
+impl<T> Buffer<T> for &ArenaBuf<'_, T> {
+    fn get(&self) -> impl Deref<Target = [T]> {
+        (*self).get()
+    }
+}
+
+impl<T, const SIZE: usize> CompressedResource<T, SIZE> {
+    /// Decompresses into `arena` rather than a `gHeap` block of its own
+    pub fn load_in<'a>(&self, arena: &'a Arena) -> Result<ArenaBuf<'a, T>, OutOfMemory> {
+        let buffer = arena.alloc_buf(SIZE)?;
+        unsafe { self.decompress_into(buffer.data.as_ptr()) };
+        Ok(buffer)
+    }
+}
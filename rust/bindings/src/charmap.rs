@@ -1,3 +1,4 @@
+use core::fmt::{self, Write as _};
 use core::ops::Deref;
 use core::ptr::slice_from_raw_parts;
 
@@ -10,6 +11,14 @@ impl Pkstr {
     pub unsafe fn from_ptr<'a>(ptr: *const u8, len: usize) -> &'a Pkstr {
         unsafe { pkstr_raw(&*slice_from_raw_parts(ptr, len)) }
     }
+    /// A string from C, up to and including its `EOS`
+    pub unsafe fn from_eos_ptr<'a>(ptr: *const u8) -> &'a Pkstr {
+        let mut len = 0;
+        while unsafe { *ptr.add(len) } != EOS {
+            len += 1;
+        }
+        unsafe { Self::from_ptr(ptr, len + 1) }
+    }
     pub const fn as_ptr(&self) -> *const u8 {
         self.0.as_ptr()
     }
@@ -19,27 +28,190 @@ impl Pkstr {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Chars and control codes, up to the end of the string
+    pub fn decode(&self) -> Decode<'_> {
+        Decode {
+            codes: self.codes(),
+        }
+    }
+
+    pub(crate) fn codes(&self) -> Codes<'_> {
+        Codes { bytes: &self.0 }
+    }
+}
+
+impl fmt::Display for Pkstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.decode().try_for_each(|decoded| write!(f, "{decoded}"))
+    }
+}
+
+impl fmt::Debug for Pkstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for decoded in self.decode() {
+            match decoded {
+                Decoded::Char(char) => write!(f, "{}", char.escape_debug())?,
+                decoded => write!(f, "{decoded}")?,
+            }
+        }
+        f.write_char('"')
+    }
 }
 
+/// An owned string of at most `CAP` bytes, `EOS` included
 pub struct ArrayPkstr<const CAP: usize>(ArrayVec<u8, CAP>);
 
 impl<const CAP: usize> ArrayPkstr<CAP> {
+    pub fn new() -> Self {
+        let mut a = ArrayVec::<u8, CAP>::new();
+        a.push(EOS);
+        Self(a)
+    }
+
     pub unsafe fn from_slice(slice: &[u8]) -> Self {
         let mut a = Self(ArrayVec::<u8, CAP>::new());
         a.0.try_extend_from_slice(slice).unwrap();
         a
     }
 
+    /// Appends a char or code before the `EOS`, unless it doesn't fit
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> bool {
+        if self.0.last() == Some(&EOS) {
+            self.0.pop();
+        }
+        let fits = self.0.remaining_capacity() > bytes.len();
+        if fits {
+            self.0.extend(bytes.iter().copied());
+        }
+        _ = self.0.try_push(EOS);
+        fits
+    }
+
+    /// Encodes `str` with `charmap.txt`, chars missing from it become `?`
     pub fn new_str(str: &str) -> Self {
         let mut a = ArrayVec::<u8, CAP>::new();
         for char in str.chars() {
-            a.push(map(char as u8));
+            a.extend(encode_char(char).unwrap_or(UNKNOWN).iter().copied());
         }
-        a.push(0xFF);
+        a.push(EOS);
         Self(a)
     }
 }
 
+impl<const CAP: usize> Default for ArrayPkstr<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes as it's written, failing on chars missing from `charmap.txt` or
+/// when full, with whatever fit still ending in `EOS`
+impl<const CAP: usize> fmt::Write for ArrayPkstr<CAP> {
+    fn write_char(&mut self, char: char) -> fmt::Result {
+        let bytes = match char {
+            SPACER => &[CHAR_SPACER],
+            char => encode_char(char).ok_or(fmt::Error)?,
+        };
+        match self.push_bytes(bytes) {
+            true => Ok(()),
+            false => Err(fmt::Error),
+        }
+    }
+
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.chars().try_for_each(|char| self.write_char(char))
+    }
+}
+
+impl<const CAP: usize> fmt::Display for ArrayPkstr<CAP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<const CAP: usize> fmt::Debug for ArrayPkstr<CAP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Formats into an [`ArrayPkstr`] of `$size` bytes, `EOS` included
+///
+/// Stops at the first char that doesn't fit or isn't in `charmap.txt`, and
+/// logs a warning, see [`try_pkformat!`] to handle it instead.
+#[macro_export]
+macro_rules! pkformat {
+    ($size:literal, $($t:tt)*) => {
+        match $crate::try_pkformat!($size, $($t)*) {
+            Ok(buf) => buf,
+            Err(buf) => {
+                $crate::warn!("Formatting cut to {:?}", buf);
+                buf
+            }
+        }
+    };
+}
+
+/// [`pkformat!`], returning what was written as an error if it didn't all fit
+#[macro_export]
+macro_rules! try_pkformat {
+    ($size:literal, $($t:tt)*) => {
+        {
+            use core::fmt::Write;
+            let mut buf = $crate::charmap::ArrayPkstr::<$size>::new();
+            match write!(buf, $($t)*) {
+                Ok(()) => Ok(buf),
+                Err(_) => Err(buf),
+            }
+        }
+    };
+}
+
+/// Padding of [`Decimal`], the `STR_CONV_MODE_*` of `ConvertIntToDecimalStringN`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvMode {
+    /// No padding
+    LeftAlign,
+    /// Padded on the left with [`SPACER`], which is as wide as a digit
+    RightAlign,
+    LeadingZeros,
+}
+
+/// A number written at least `digits` wide, like `ConvertUIntToDecimalStringN`
+///
+/// Values wider than `digits` are written in full rather than with a `?`.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    pub value: u32,
+    pub mode: ConvMode,
+    pub digits: u8,
+}
+
+pub const fn decimal(value: u32, mode: ConvMode, digits: u8) -> Decimal {
+    Decimal {
+        value,
+        mode,
+        digits,
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pad = match self.mode {
+            ConvMode::LeftAlign => return write!(f, "{}", self.value),
+            ConvMode::RightAlign => SPACER,
+            ConvMode::LeadingZeros => '0',
+        };
+        let width = self.value.checked_ilog10().unwrap_or(0) + 1;
+        for _ in width..self.digits as u32 {
+            f.write_char(pad)?;
+        }
+        write!(f, "{}", self.value)
+    }
+}
+
 impl<const CAP: usize> Deref for ArrayPkstr<CAP> {
     type Target = Pkstr;
     fn deref(&self) -> &Self::Target {
@@ -47,36 +219,251 @@ impl<const CAP: usize> Deref for ArrayPkstr<CAP> {
     }
 }
 
-const fn map(char: u8) -> u8 {
-    match char {
-        c @ b'a'..=b'z' => c - b'a' + 0xd5,
-        c @ b'A'..=b'Z' => c - b'A' + 0xbb,
-        c @ b'0'..=b'9' => c - b'0' + 0xa1,
-        b' ' => 0x00,
-        b'!' => 0xAB,
-        b'?' => 0xAC,
-        b'.' => 0xAD,
-        b'-' => 0xAE,
-        b'_' => 0xAE,
-        b':' => 0xF0,
-        b'>' => 0x86,
-        b'<' => 0x85,
-        b')' => 0x5D,
-        b'(' => 0x5C,
-        b',' => 0x35,
-        b'+' => 0x2E,
-        b'&' => 0x2D,
-        b'/' => 0xBA,
-        0 => 0xFF,
-        _ => 0xAE,
+include!(concat!(env!("OUT_DIR"), "/charmap.rs"));
+
+/// End of string
+pub const EOS: u8 = 0xFF;
+/// Blank as wide as a digit, `CHAR_SPACER`
+const CHAR_SPACER: u8 = 0x77;
+/// Char written as `CHAR_SPACER`, which has none in `charmap.txt`
+pub const SPACER: char = '\u{2007}';
+const CHAR_DYNAMIC: u8 = 0xF7;
+const CHAR_KEYPAD_ICON: u8 = 0xF8;
+const CHAR_EXTRA_SYMBOL: u8 = 0xF9;
+const EXT_CTRL_CODE_BEGIN: u8 = 0xFC;
+pub(crate) const PLACEHOLDER_BEGIN: u8 = 0xFD;
+
+/// Bytes of each `EXT_CTRL_CODE_*` with its arguments, from `GetExtCtrlCodeLength`
+const EXT_CTRL_CODE_LENGTHS: [u8; 0x19] = [
+    1, 2, 2, 2, 4, 2, 2, 1, 2, 1, 1, 3, 2, 2, 2, 1, 3, 2, 2, 2, 2, 1, 1, 1, 1,
+];
+
+const fn ext_ctrl_code_length(code: u8) -> usize {
+    match code as usize {
+        code if code < EXT_CTRL_CODE_LENGTHS.len() => EXT_CTRL_CODE_LENGTHS[code] as usize,
+        _ => 1,
+    }
+}
+
+/// Bytes taken by the code or char starting with `bytes[0]`
+const fn code_length(bytes: &[u8]) -> usize {
+    match bytes[0] {
+        CHAR_DYNAMIC | CHAR_KEYPAD_ICON | CHAR_EXTRA_SYMBOL | PLACEHOLDER_BEGIN => 2,
+        EXT_CTRL_CODE_BEGIN if bytes.len() > 1 => 1 + ext_ctrl_code_length(bytes[1]),
+        _ => 1,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoded<'a> {
+    Char(char),
+    /// A `{NAME}` code of `charmap.txt`, with the argument bytes following it
+    Code(&'static str, &'a [u8]),
+    /// A byte that means nothing on its own
+    Unknown(u8),
+}
+
+impl fmt::Display for Decoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoded::Char(char) => f.write_char(*char),
+            Decoded::Code(name, args) => {
+                write!(f, "{{{name}")?;
+                for arg in *args {
+                    write!(f, " {arg}")?;
+                }
+                f.write_char('}')
+            }
+            Decoded::Unknown(byte) => write!(f, "{{{byte:#04X}}}"),
+        }
+    }
+}
+
+/// Raw chars and control codes with their arguments, up to `EOS`
+pub(crate) struct Codes<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Codes<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.bytes.first() {
+            None | Some(&EOS) => None,
+            Some(_) => {
+                let len = code_length(self.bytes).min(self.bytes.len());
+                let (code, rest) = self.bytes.split_at(len);
+                self.bytes = rest;
+                Some(code)
+            }
+        }
     }
 }
 
-const fn map_special(bytes: &[u8]) -> &'static [u8] {
-    match bytes {
-        b"PAUSE" => &[0xFC, 0x09],
-        b"PARAGRAPH" | b"P" => &[0xFB],
-        _ => panic!("Invalid special char"),
+pub struct Decode<'a> {
+    codes: Codes<'a>,
+}
+
+impl<'a> Iterator for Decode<'a> {
+    type Item = Decoded<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let code = self.codes.next()?;
+        let first = code[0];
+        if first == CHAR_SPACER {
+            return Some(Decoded::Char(SPACER));
+        }
+        if let (Some(char), 1) = (decode_char(first), code.len()) {
+            return Some(Decoded::Char(char));
+        }
+        // A name for the whole code like `{FONT_SMALL}`, else for its start like `{FONT 0}`
+        (1..=code.len())
+            .rev()
+            .find_map(|prefix| decode_name(&code[..prefix]).map(|name| (name, &code[prefix..])))
+            .map(|(name, args)| Decoded::Code(name, args))
+            .or(Some(Decoded::Unknown(first)))
+    }
+}
+
+const UNKNOWN: &[u8] = match encode_char('?') {
+    Some(bytes) => bytes,
+    None => panic!("No ? in charmap.txt"),
+};
+
+/// A `{NAME}` code, with the short `{PARAGRAPH}` alias of `{P}`
+///
+/// Names follow charmap.txt: `{PAUSE}` is FC 08 and takes a duration, waiting
+/// for a button press is `{PAUSE_UNTIL_PRESS}`.
+const fn special(name: &[u8]) -> &'static [u8] {
+    let name = match name {
+        b"PARAGRAPH" => b"P".as_slice(),
+        name => name,
+    };
+    match encode_name(name) {
+        Some(bytes) => bytes,
+        None => panic!("Unknown {{NAME}} code, see charmap.txt"),
+    }
+}
+
+/// Longest `{NAME args}` code once encoded
+const MAX_CODE_LEN: usize = 8;
+
+const fn push_code(code: &mut [u8; MAX_CODE_LEN], len: &mut usize, bytes: &[u8]) {
+    let mut i = 0;
+    while i < bytes.len() {
+        if *len == MAX_CODE_LEN {
+            panic!("{{NAME}} code too long");
+        }
+        code[*len] = bytes[i];
+        *len += 1;
+        i += 1;
+    }
+}
+
+/// A decimal or `0x` hex argument
+const fn parse_arg(word: &[u8]) -> u8 {
+    let (radix, digits) = match word {
+        [b'0', b'x' | b'X', digits @ ..] => (16, digits),
+        digits => (10, digits),
+    };
+    if digits.is_empty() {
+        panic!("Empty number in a {{NAME}} code");
+    }
+    let mut value: u32 = 0;
+    let mut i = 0;
+    while i < digits.len() {
+        let digit = match digits[i] {
+            digit @ b'0'..=b'9' => digit - b'0',
+            digit @ b'a'..=b'f' if radix == 16 => digit - b'a' + 10,
+            digit @ b'A'..=b'F' if radix == 16 => digit - b'A' + 10,
+            _ => panic!("Bad number in a {{NAME}} code"),
+        };
+        value = value * radix + digit as u32;
+        if value > u8::MAX as u32 {
+            panic!("Argument of a {{NAME}} code over 255");
+        }
+        i += 1;
+    }
+    value as u8
+}
+
+/// Encodes a `{NAME args}` code, like `{COLOR RED}`, `{PAUSE 15}` or `{CLEAR_TO 0x48}`
+///
+/// Each argument is a number or a `{NAME}` such as `RED`. The argument count
+/// of control codes is checked against `GetExtCtrlCodeLength`.
+const fn encode_code(content: &[u8]) -> ([u8; MAX_CODE_LEN], usize) {
+    let mut code = [0; MAX_CODE_LEN];
+    let mut len = 0;
+    let mut name: &[u8] = &[];
+    let mut name_len = 0;
+    let mut start = 0;
+    while start < content.len() {
+        if content[start] == b' ' {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < content.len() && content[end] != b' ' {
+            end += 1;
+        }
+        let (word, _) = content.split_at(end);
+        let (_, word) = word.split_at(start);
+        match (name_len, word[0]) {
+            (0, _) => {
+                push_code(&mut code, &mut len, special(word));
+                name = word;
+                name_len = len;
+            }
+            (_, b'0'..=b'9') => push_code(&mut code, &mut len, &[parse_arg(word)]),
+            _ => push_code(&mut code, &mut len, special(word)),
+        }
+        start = end;
+    }
+    if name_len == 0 {
+        panic!("Empty {{}} code");
+    }
+
+    // Find where the code the name ends in stops, which is where its arguments end
+    let (bytes, _) = code.split_at(len);
+    let mut end = 0;
+    while end < name_len {
+        let (_, rest) = bytes.split_at(end);
+        end += code_length(rest);
+    }
+    match end {
+        end if end > len => match name {
+            b"PAUSE" => panic!("{{PAUSE}} takes a duration, use {{PAUSE_UNTIL_PRESS}} to wait"),
+            _ => panic!("Missing arguments for a {{NAME}} code"),
+        },
+        end if end < len => panic!("Too many arguments for a {{NAME}} code"),
+        _ => (code, len),
+    }
+}
+
+/// Decodes the UTF-8 char at `index`, returning it and its length
+const fn next_char(input: &[u8], index: usize) -> (char, usize) {
+    let first = input[index] as u32;
+    let (len, mut value) = match first {
+        0..0x80 => (1, first),
+        0xC0..0xE0 => (2, first & 0x1F),
+        0xE0..0xF0 => (3, first & 0x0F),
+        _ => (4, first & 0x07),
+    };
+    let mut i = 1;
+    while i < len {
+        value = value << 6 | (input[index + i] as u32 & 0x3F);
+        i += 1;
+    }
+    match char::from_u32(value) {
+        Some(char) => (char, len),
+        None => panic!("Invalid UTF-8"),
+    }
+}
+
+const fn char_bytes(char: char) -> &'static [u8] {
+    match encode_char(char) {
+        Some(bytes) => bytes,
+        None => panic!("Char not in charmap.txt"),
     }
 }
 
@@ -84,8 +471,8 @@ const fn map_special(bytes: &[u8]) -> &'static [u8] {
 macro_rules! pkstr {
     ($str:literal) => {{
         use $crate::charmap::*;
-        const LEN: usize = pkstr_bytes_len($str);
-        const ARR: [u8; LEN] = pkstr_build::<LEN>($str);
+        const LEN: usize = pkstr_bytes_len($str.as_bytes());
+        const ARR: [u8; LEN] = pkstr_build::<LEN>($str.as_bytes());
         unsafe { pkstr_raw(&ARR) }
     }};
 }
@@ -109,11 +496,12 @@ pub const fn pkstr_bytes_len(input: &[u8]) -> usize {
             let (_, remaining) = input.split_at(index + 1);
             let content_len = index_of(remaining, b'}');
             let (content, _) = remaining.split_at(content_len);
-            size += map_special(content).len();
+            size += encode_code(content).1;
             index += content_len + 2;
         } else {
-            size += 1;
-            index += 1;
+            let (char, len) = next_char(input, index);
+            size += char_bytes(char).len();
+            index += len;
         }
     }
     size + 1
@@ -129,21 +517,26 @@ pub const fn pkstr_write(buf: &mut [u8], input: &[u8]) {
             let (content, _) = remaining.split_at(content_len);
             index += content_len + 2;
 
-            let to_write = map_special(content);
-            let (_, write) = buf.split_at_mut(offset);
-            let mut write_index = 0;
-            while write_index < to_write.len() {
-                write[write_index] = to_write[write_index];
-                write_index += 1;
+            let (code, len) = encode_code(content);
+            let mut byte = 0;
+            while byte < len {
+                buf[offset] = code[byte];
+                byte += 1;
                 offset += 1;
             }
         } else {
-            buf[offset] = map(input[index]);
-            index += 1;
-            offset += 1;
+            let (char, len) = next_char(input, index);
+            let bytes = char_bytes(char);
+            let mut byte = 0;
+            while byte < bytes.len() {
+                buf[offset] = bytes[byte];
+                byte += 1;
+                offset += 1;
+            }
+            index += len;
         }
     }
-    buf[offset] = 0xFF;
+    buf[offset] = EOS;
 }
 
 pub const fn pkstr_build<const S: usize>(input: &[u8]) -> [u8; S] {
@@ -0,0 +1,193 @@
+//! Crash screen drawn by the panic handler
+//!
+//! The C graphics code may be what panicked, so this takes over the hardware
+//! directly: interrupts and DMA are stopped, and the panic is drawn to the
+//! mode 3 bitmap with the rom's small font.
+
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::heap::heap_stats;
+use crate::input::Button;
+use crate::log::RING;
+use crate::pokeemerald::{
+    DISPCNT_BG2_ON, DISPCNT_MODE_3, HEAP_SIZE, REG_ADDR_BG2CNT, REG_ADDR_BG2PA, REG_ADDR_BG2PB,
+    REG_ADDR_BG2PC, REG_ADDR_BG2PD, REG_ADDR_BG2X, REG_ADDR_BG2Y, REG_ADDR_BLDCNT,
+    REG_ADDR_DISPCNT, REG_ADDR_DMA0CNT_H, REG_ADDR_IME, REG_ADDR_KEYINPUT, REG_ADDR_MOSAIC,
+    RESET_ALL, SoftReset, VRAM,
+};
+use crate::{charmap, stack_size};
+
+unsafe extern "C" {
+    static gFontSmallNarrowLatinGlyphs: [u16; 0];
+    static gFontSmallNarrowLatinGlyphWidths: [u8; 0];
+}
+
+const WIDTH: usize = 240;
+const HEIGHT: usize = 160;
+const MARGIN: usize = 4;
+const LINE_HEIGHT: usize = 12;
+
+/// Glyph for chars missing from the font, or taking more than a byte
+const UNKNOWN: u8 = match charmap::encode_char('?') {
+    Some(&[id]) => id,
+    _ => panic!("No ? in charmap.txt"),
+};
+
+const BACKGROUND: u16 = 0x2842;
+const FOREGROUND: u16 = 0x7FFF;
+const SHADOW: u16 = 0x318C;
+
+/// Set once the crash screen starts, so a panic while drawing it doesn't recurse
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+unsafe fn write_reg<T>(addr: u32, value: T) {
+    unsafe { (addr as *mut T).write_volatile(value) }
+}
+
+/// Text cursor over the mode 3 bitmap, dropping whatever doesn't fit
+struct Screen {
+    x: usize,
+    y: usize,
+    /// Cut lines at the edge of the screen instead of wrapping them
+    clip: bool,
+}
+
+impl Screen {
+    /// Stops everything that could still touch the screen and clears it
+    fn take_over() -> Screen {
+        unsafe {
+            write_reg::<u16>(REG_ADDR_IME, 0);
+            for channel in 0..4 {
+                write_reg::<u16>(REG_ADDR_DMA0CNT_H + channel * 12, 0);
+            }
+            write_reg::<u16>(REG_ADDR_DISPCNT, (DISPCNT_MODE_3 | DISPCNT_BG2_ON) as u16);
+            write_reg::<u16>(REG_ADDR_BG2CNT, 0);
+            write_reg::<u16>(REG_ADDR_BLDCNT, 0);
+            write_reg::<u16>(REG_ADDR_MOSAIC, 0);
+            write_reg::<u16>(REG_ADDR_BG2PA, 0x100);
+            write_reg::<u16>(REG_ADDR_BG2PB, 0);
+            write_reg::<u16>(REG_ADDR_BG2PC, 0);
+            write_reg::<u16>(REG_ADDR_BG2PD, 0x100);
+            write_reg::<u32>(REG_ADDR_BG2X, 0);
+            write_reg::<u32>(REG_ADDR_BG2Y, 0);
+        }
+        for offset in 0..WIDTH * HEIGHT {
+            unsafe { (VRAM as *mut u16).add(offset).write_volatile(BACKGROUND) };
+        }
+        Screen {
+            x: MARGIN,
+            y: MARGIN,
+            clip: false,
+        }
+    }
+
+    fn newline(&mut self) {
+        self.x = MARGIN;
+        self.y += LINE_HEIGHT;
+    }
+
+    /// Draws a glyph of the small font, 16x16 cells stored as four 2bpp tiles
+    fn glyph(&mut self, id: u8) {
+        let id = id as usize;
+        let width = unsafe { *gFontSmallNarrowLatinGlyphWidths.as_ptr().add(id) } as usize;
+        if self.x + width > WIDTH - MARGIN {
+            match self.clip {
+                true => return,
+                false => self.newline(),
+            }
+        }
+        if self.y + LINE_HEIGHT > HEIGHT {
+            return;
+        }
+
+        let glyph = unsafe { gFontSmallNarrowLatinGlyphs.as_ptr().add(id * 0x20) };
+        for row in 0..LINE_HEIGHT {
+            for col in 0..width.min(16) {
+                let tile = (row / 8) * 2 + col / 8;
+                let pixels = unsafe { *glyph.add(tile * 8 + row % 8) };
+                // Left half in the high byte, leftmost pixel in the top bits
+                let half = match col % 8 < 4 {
+                    true => pixels >> 8,
+                    false => pixels & 0xFF,
+                };
+                let color = match half >> (6 - 2 * (col % 4)) & 3 {
+                    1 => FOREGROUND,
+                    2 => SHADOW,
+                    _ => continue,
+                };
+                let offset = (self.y + row) * WIDTH + self.x + col;
+                unsafe { (VRAM as *mut u16).add(offset).write_volatile(color) };
+            }
+        }
+        self.x += width;
+    }
+}
+
+impl Write for Screen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for char in s.chars() {
+            match char {
+                '\n' => self.newline(),
+                char => match charmap::encode_char(char) {
+                    Some(&[id]) => self.glyph(id),
+                    _ => self.glyph(UNKNOWN),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shows the panic on screen and waits for START to soft reset
+pub(crate) fn crash_screen(info: &PanicInfo) -> ! {
+    if CRASHED.load(Ordering::Relaxed) {
+        loop {}
+    }
+    CRASHED.store(true, Ordering::Relaxed);
+
+    let stack = stack_size();
+    let mut screen = Screen::take_over();
+    #[cfg(feature = "sram-dump")]
+    unsafe {
+        RING.dump_to_sram()
+    };
+    match info.location() {
+        Some(location) => _ = writeln!(screen, "Panic at {location}"),
+        None => _ = writeln!(screen, "Panic"),
+    }
+    _ = writeln!(screen, "{}\n", info.message());
+    _ = writeln!(screen, "Stack: {stack} bytes");
+    let heap = heap_stats();
+    _ = writeln!(
+        screen,
+        "Heap: {} live, {} peak, {} blocks",
+        heap.live, heap.peak, heap.allocations
+    );
+    _ = writeln!(
+        screen,
+        "gHeap: {}/{HEAP_SIZE} used, {} largest free",
+        heap.heap_used, heap.largest_free
+    );
+
+    // The last log lines that fit, one per row
+    let bottom = HEIGHT - MARGIN - LINE_HEIGHT;
+    let rows = bottom.saturating_sub(screen.y + LINE_HEIGHT) / LINE_HEIGHT;
+    screen.newline();
+    screen.clip = true;
+    RING.tail(rows, |level, line| {
+        let line = line.lines().next().unwrap_or_default();
+        _ = writeln!(screen, "{} {line}", level.letter());
+    });
+
+    screen.x = MARGIN;
+    screen.y = bottom;
+    _ = write!(screen, "Press START to reset");
+
+    let keys = || unsafe { !(REG_ADDR_KEYINPUT as *const u16).read_volatile() };
+    while keys() & Button::Start.code() != 0 {}
+    while keys() & Button::Start.code() == 0 {}
+    unsafe { SoftReset(RESET_ALL) };
+    loop {}
+}
@@ -1,10 +1,16 @@
 use alloc::boxed::Box;
 use core::cell::RefCell;
+use core::ffi::CStr;
 use core::future::Future;
 use core::ops::Deref;
 use core::pin::Pin;
 use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
 
+use crate::heap::{HeapScope, OutOfMemory, log_heap_stats};
+use crate::pokeemerald::{MainCallback, SetMainCallback2, SetVBlankHBlankCallbacksToNull};
+use crate::stack::{self, StackScope};
+use crate::{error, resources};
+
 pub(crate) fn dummy_raw_waker() -> RawWaker {
     static VTABLE: RawWakerVTable =
         RawWakerVTable::new(|_| dummy_raw_waker(), |_| {}, |_| {}, |_| {});
@@ -17,12 +23,36 @@ pub(crate) fn dummy_waker() -> Waker {
 
 pub(crate) struct FuturePoll {
     pub(crate) future: Option<Pin<Box<dyn Future<Output = ()>>>>,
+    scope: Option<HeapScope>,
+    stack: Option<StackScope>,
 }
 
 impl FuturePoll {
-    fn new(obj: Box<dyn Future<Output = ()>>) -> FuturePoll {
+    /// Boxes `fut` once the scopes are entered, so the box counts as the scene's
+    fn new<F>(tag: &'static CStr, fut: F) -> FuturePoll
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let scope = HeapScope::enter(tag);
+        let stack = StackScope::enter(tag);
         FuturePoll {
-            future: Some(Pin::from(obj)),
+            future: Some(Box::pin(fut)),
+            scope: Some(scope),
+            stack: Some(stack),
+        }
+    }
+
+    /// Drops the scene's future, then reports what it left on the heap and its stack usage
+    ///
+    /// Resources it cached stay around for the next scene, up to the cache budget.
+    fn finish(&mut self) {
+        self.future = None;
+        resources::trim_cache();
+        if let Some(scope) = self.scope.take() {
+            scope.exit();
+        }
+        if let Some(stack) = self.stack.take() {
+            stack.exit();
         }
     }
 
@@ -33,9 +63,11 @@ impl FuturePoll {
 
         let waker = dummy_waker();
         let mut context = Context::from_waker(&waker);
-        match future.as_mut().poll(&mut context) {
+        let poll = future.as_mut().poll(&mut context);
+        stack::check();
+        match poll {
             Poll::Ready(()) => {
-                self.future = None;
+                self.finish();
                 return Some(Done);
             }
             Poll::Pending => {}
@@ -81,11 +113,41 @@ pub struct Executor(RefCellSync<FuturePoll>);
 
 impl Executor {
     pub const fn new() -> Executor {
-        Executor(RefCellSync::new(FuturePoll { future: None }))
+        Executor(RefCellSync::new(FuturePoll {
+            future: None,
+            scope: None,
+            stack: None,
+        }))
+    }
+
+    /// Runs `fut` as a scene, its allocations tagged with `tag`
+    pub fn set<F>(&self, tag: &'static CStr, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let mut poll = self.0.borrow_mut();
+        poll.finish();
+        *poll = FuturePoll::new(tag, fut)
     }
 
-    pub fn set(&self, fut: Box<dyn Future<Output = ()>>) {
-        *self.0.borrow_mut() = FuturePoll::new(fut)
+    /// Runs a scene that can run out of memory, going back to `back` if it does
+    ///
+    /// Returning the error drops everything the scene held, so the heap and
+    /// the sprite and window pools are released before `back` takes over.
+    pub fn set_scene<F>(&self, tag: &'static CStr, back: MainCallback, fut: F)
+    where
+        F: Future<Output = Result<(), OutOfMemory>> + 'static,
+    {
+        self.set(tag, async move {
+            if let Err(err) = fut.await {
+                error!("{tag:?} ran out of memory: {err:?}");
+                log_heap_stats();
+                unsafe {
+                    SetVBlankHBlankCallbacksToNull();
+                    SetMainCallback2(back);
+                }
+            }
+        })
     }
 
     pub fn poll(&self) -> Option<Done> {
@@ -1,18 +1,24 @@
 use alloc::boxed::Box;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::any::Any;
 use core::array;
+use core::cell::Cell;
+use core::cmp::min;
 use core::marker::PhantomData;
 use core::ops::{BitOr, Deref, Mul};
-use core::ptr::null_mut;
+use core::ptr::{null, null_mut};
 
 use derive_more::{Add, Constructor, Div, Mul, Sub};
 
 use crate::charmap::Pkstr;
-use crate::future::sleep;
+use crate::future::{RefCellSync, sleep};
+use crate::heap::{OutOfMemory, heap_stats, try_box};
 use crate::pokeemerald::{self, *};
-use crate::resources::{Buffer, static_deref};
-use crate::{data, mgba_warn};
+use crate::resources::{
+    AllocBuf, Buffer, CompressedResource, TilemapResource, static_deref, static_index,
+};
+use crate::{data, debug, trace};
 
 pub fn set_gpu_registers(list: &[(u32, &[u32])]) {
     for (offset, flags) in list {
@@ -88,9 +94,19 @@ impl Rect<u8> {
             height: self.height as u16 * 8,
         }
     }
+
+    /// Tile positions inside the rect, row by row, panicking if some don't fit in a `u8`
+    pub fn positions(self) -> impl Iterator<Item = Vec2D<u8>> {
+        let (x, y) = (self.x as u16, self.y as u16);
+        let (right, bottom) = (x + self.width as u16, y + self.height as u16);
+        if right > 0x100 || bottom > 0x100 {
+            panic!("{self:?} goes past tile 255");
+        }
+        (y..bottom).flat_map(move |y| (x..right).map(move |x| Vec2D::new(x as u8, y as u8)))
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct BgPalette {
     index: u8,
 }
@@ -183,30 +199,199 @@ pub async fn fade_palette(mask: PaletteMask, delay: i8, start: u8, target: u8, c
 
 #[derive(Clone, Copy, Debug)]
 pub struct TileBitmap4bpp(pub [u8; 32]);
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Tile4bpp(pub u16);
+
+impl Tile4bpp {
+    const INDEX_MASK: u16 = 0x3FF;
+    const HFLIP: u16 = 1 << 10;
+    const VFLIP: u16 = 1 << 11;
+    const PALETTE_SHIFT: u16 = 12;
+
+    pub const fn new(index: u16, palette: BgPalette) -> Self {
+        Tile4bpp((index & Self::INDEX_MASK) | (palette.index as u16) << Self::PALETTE_SHIFT)
+    }
+
+    pub const fn index(self) -> u16 {
+        self.0 & Self::INDEX_MASK
+    }
+    pub const fn hflip(self) -> bool {
+        self.0 & Self::HFLIP != 0
+    }
+    pub const fn vflip(self) -> bool {
+        self.0 & Self::VFLIP != 0
+    }
+    pub const fn palette(self) -> BgPalette {
+        BgPalette {
+            index: (self.0 >> Self::PALETTE_SHIFT) as u8,
+        }
+    }
+
+    pub const fn with_index(self, index: u16) -> Self {
+        Tile4bpp((self.0 & !Self::INDEX_MASK) | (index & Self::INDEX_MASK))
+    }
+    pub const fn with_hflip(self, hflip: bool) -> Self {
+        match hflip {
+            true => Tile4bpp(self.0 | Self::HFLIP),
+            false => Tile4bpp(self.0 & !Self::HFLIP),
+        }
+    }
+    pub const fn with_vflip(self, vflip: bool) -> Self {
+        match vflip {
+            true => Tile4bpp(self.0 | Self::VFLIP),
+            false => Tile4bpp(self.0 & !Self::VFLIP),
+        }
+    }
+    pub const fn with_palette(self, palette: BgPalette) -> Self {
+        let mask = (1 << Self::PALETTE_SHIFT) - 1;
+        Tile4bpp((self.0 & mask) | (palette.index as u16) << Self::PALETTE_SHIFT)
+    }
+}
 #[derive(Clone, Copy, Debug)]
 pub struct TilePlain(pub u8);
+#[derive(Clone, Copy, Debug)]
+pub struct TileBitmap8bpp(pub [u8; 64]);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileAffine(pub u8);
+
+pub trait TileBitmap {
+    const PALETTE_MODE: u16;
+}
+impl TileBitmap for TileBitmap4bpp {
+    const PALETTE_MODE: u16 = 0;
+}
+impl TileBitmap for TileBitmap8bpp {
+    const PALETTE_MODE: u16 = 1;
+}
 
 #[derive(Clone, Copy, Debug)]
-pub struct Tileset<Buf: Buffer<TileBitmap4bpp>> {
+pub struct Tileset<Buf> {
     pub char_base: u16,
     pub offset: u16,
     pub tiles: Buf,
     pub palette: BgPalette,
 }
 
+impl<B> Tileset<&'static [B]> {
+    /// Tileset whose tiles are loaded separately, e.g. with [`BgHandle::load_tiles`]
+    pub const fn in_vram(char_base: u16, offset: u16, palette: BgPalette) -> Self {
+        Tileset {
+            char_base,
+            offset,
+            tiles: &[],
+            palette,
+        }
+    }
+}
+
 pub struct TilesetHandle {
     pub char_base: u16,
     pub offset: u16,
     pub palette: BgPalette,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScreenSize {
+    #[default]
+    Size256x256 = 0,
+    Size512x256 = 1,
+    Size256x512 = 2,
+    Size512x512 = 3,
+}
+
+impl ScreenSize {
+    pub const fn tiles(self) -> Vec2D<u16> {
+        match self {
+            ScreenSize::Size256x256 => Vec2D::new(32, 32),
+            ScreenSize::Size512x256 => Vec2D::new(64, 32),
+            ScreenSize::Size256x512 => Vec2D::new(32, 64),
+            ScreenSize::Size512x512 => Vec2D::new(64, 64),
+        }
+    }
+
+    pub const fn tilemap_bytes(self) -> usize {
+        let tiles = self.tiles();
+        tiles.x as usize * tiles.y as usize * size_of::<Tile4bpp>()
+    }
+
+    pub const fn from_tiles(width: usize, height: usize) -> Option<Self> {
+        match (width, height) {
+            (32, 32) => Some(ScreenSize::Size256x256),
+            (64, 32) => Some(ScreenSize::Size512x256),
+            (32, 64) => Some(ScreenSize::Size256x512),
+            (64, 64) => Some(ScreenSize::Size512x512),
+            _ => None,
+        }
+    }
+}
+
+/// Affine backgrounds are always square, from 16x16 up to 128x128 tiles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AffineSize {
+    #[default]
+    Size128 = 0,
+    Size256 = 1,
+    Size512 = 2,
+    Size1024 = 3,
+}
+
+impl AffineSize {
+    pub const fn tiles(self) -> u16 {
+        16 << self as u16
+    }
+
+    pub const fn tilemap_bytes(self) -> usize {
+        let tiles = self.tiles() as usize;
+        tiles * tiles * size_of::<TileAffine>()
+    }
+}
+
 pub struct Tilemap<Buf: Buffer<Tile4bpp>> {
     pub map: u16,
+    pub size: ScreenSize,
     pub buffer: Buf,
 }
 
+impl Tilemap<AllocBuf<Tile4bpp>> {
+    pub fn empty(map: u16, size: ScreenSize) -> Self {
+        let buffer = AllocBuf::new(vec![0u8; size.tilemap_bytes()].into_boxed_slice());
+        Tilemap { map, size, buffer }
+    }
+
+    /// Fails to compile unless the resource covers a whole [`ScreenSize`]
+    pub fn load<const W: usize, const H: usize>(
+        map: u16,
+        resource: &TilemapResource<Tile4bpp, W, H>,
+    ) -> Self {
+        let size = const { TilemapResource::<Tile4bpp, W, H>::SCREEN_SIZE };
+        Tilemap {
+            map,
+            size,
+            buffer: resource.load(),
+        }
+    }
+}
+
+pub struct AffineTilemap<Buf: Buffer<TileAffine>> {
+    pub map: u16,
+    pub size: AffineSize,
+    pub buffer: Buf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BgMode {
+    /// All four backgrounds are text backgrounds
+    Text = 0,
+    /// Backgrounds 0 and 1 are text, background 2 is affine
+    Mixed = 1,
+    /// Backgrounds 2 and 3 are affine, 0 and 1 are unavailable
+    Affine = 2,
+}
+
+pub fn set_bg_mode(mode: BgMode) {
+    unsafe { SetBgMode(mode as u32) };
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum BackgroundIndex {
     Background0 = 0,
@@ -215,6 +400,19 @@ pub enum BackgroundIndex {
     Background3 = 3,
 }
 
+/// Backgrounds whose tilemap buffer was edited since the last scheduled copy
+static DIRTY_TILEMAPS: RefCellSync<u8> = RefCellSync::new(0);
+
+/// Schedules the vram copy of every background edited through [`BgHandle::set_tile`] and co.
+pub fn schedule_dirty_tilemaps() {
+    let dirty = core::mem::take(&mut *DIRTY_TILEMAPS.borrow_mut());
+    for bg in 0..4u8 {
+        if dirty & (1 << bg) != 0 {
+            unsafe { ScheduleBgCopyTilemapToVram(bg) };
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct BgHandle<'a>(BackgroundIndex, PhantomData<&'a ()>);
 impl Deref for BgHandle<'_> {
@@ -237,12 +435,100 @@ impl BgHandle<'_> {
         }
     }
 
+    /// Decompresses tiles straight into the background's char block, starting at tile `offset`
+    pub fn load_tiles<B: TileBitmap, const SIZE: usize>(
+        &self,
+        tiles: &CompressedResource<B, SIZE>,
+        offset: u16,
+    ) {
+        let char_base = unsafe { GetBgAttribute(self.0 as _, BG_ATTR_CHARBASEINDEX) } as usize;
+        let start = char_base * BG_CHAR_SIZE as usize + offset as usize * size_of::<B>();
+        if start + SIZE > BG_VRAM_SIZE as usize {
+            panic!("Tiles don't fit in bg vram");
+        }
+        unsafe { tiles.load_to_vram((VRAM as usize + start) as *mut B) };
+    }
+
     pub fn schedule_copy_tilemap(&self) {
+        *DIRTY_TILEMAPS.borrow_mut() &= !(1 << self.0 as u8);
         unsafe {
             ScheduleBgCopyTilemapToVram(self.0 as u8);
         }
     }
 
+    /// Schedules the vram copy only if the tilemap buffer was edited
+    pub fn flush(&self) {
+        if self.is_dirty() {
+            self.schedule_copy_tilemap();
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        *DIRTY_TILEMAPS.borrow() & (1 << self.0 as u8) != 0
+    }
+
+    fn mark_dirty(&self) {
+        *DIRTY_TILEMAPS.borrow_mut() |= 1 << self.0 as u8;
+    }
+
+    /// Entry of a text background's tilemap, affine ones have 8 bit entries instead
+    fn tilemap_entry(&self, pos: Vec2D<u8>) -> *mut Tile4bpp {
+        unsafe {
+            let bg = self.0 as u32;
+            if GetBgAttribute(bg, BG_ATTR_TYPE) != BG_TYPE_NORMAL as u16 {
+                panic!("Background {bg} is not a text background");
+            }
+            let buffer: *mut Tile4bpp = GetBgTilemapBuffer(bg).cast();
+            if buffer.is_null() {
+                panic!("Background {bg} has no tilemap buffer");
+            }
+            let screen_size = GetBgAttribute(bg, BG_ATTR_SCREENSIZE);
+            let width = GetBgMetricTextMode(bg, 1) as u32 * 32;
+            let height = GetBgMetricTextMode(bg, 2) as u32 * 32;
+            let index =
+                GetTileMapIndexFromCoords(pos.x as _, pos.y as _, screen_size as _, width, height);
+            buffer.add(index as usize)
+        }
+    }
+
+    pub fn get_tile(&self, pos: Vec2D<u8>) -> Tile4bpp {
+        unsafe { *self.tilemap_entry(pos) }
+    }
+
+    pub fn set_tile(&self, pos: Vec2D<u8>, tile: Tile4bpp) {
+        let entry = unsafe { &mut *self.tilemap_entry(pos) };
+        if *entry != tile {
+            *entry = tile;
+            self.mark_dirty();
+        }
+    }
+
+    /// Replaces every tile in `rect` with the result of `f`
+    pub fn map_rect(&self, rect: Rect<u8>, mut f: impl FnMut(Vec2D<u8>, Tile4bpp) -> Tile4bpp) {
+        for pos in rect.positions() {
+            self.set_tile(pos, f(pos, self.get_tile(pos)));
+        }
+    }
+
+    /// Moves the tiles inside `rect` by `delta`, uncovered tiles are set to `fill`
+    pub fn shift(&self, rect: Rect<u8>, delta: Vec2D<i8>, fill: Tile4bpp) {
+        let (width, height) = (rect.width as i16, rect.height as i16);
+        for row in 0..height {
+            // Walk against the shift direction so sources are read before being overwritten
+            let y = if delta.y > 0 { height - 1 - row } else { row };
+            for col in 0..width {
+                let x = if delta.x > 0 { width - 1 - col } else { col };
+                let (src_x, src_y) = (x - delta.x as i16, y - delta.y as i16);
+                let inside = (0..width).contains(&src_x) && (0..height).contains(&src_y);
+                let tile = match inside {
+                    true => self.get_tile(Vec2D::new(rect.x + src_x as u8, rect.y + src_y as u8)),
+                    false => fill,
+                };
+                self.set_tile(Vec2D::new(rect.x + x as u8, rect.y + y as u8), tile);
+            }
+        }
+    }
+
     pub fn set_pos(&self, x: u8, y: u8) {
         unsafe {
             ChangeBgX(self.0 as _, BG_COORD_SET as _, x);
@@ -293,6 +579,39 @@ impl BgHandle<'_> {
     }
 }
 
+struct BgLayout {
+    index: BackgroundIndex,
+    priority: u16,
+    char_base: u16,
+    map_base: u16,
+    base_tile: u16,
+    palette_mode: u16,
+    screen_size: u16,
+}
+
+impl BgLayout {
+    fn init(&self, tiles: (*const u8, usize), map: (*const u8, usize)) {
+        let mut template: BgTemplate = BgTemplate::default();
+        template.set_bg(self.index as u16);
+        template.set_charBaseIndex(self.char_base);
+        template.set_mapBaseIndex(self.map_base);
+        template.set_baseTile(self.base_tile);
+        template.set_paletteMode(self.palette_mode);
+        template.set_priority(self.priority);
+        template.set_screenSize(self.screen_size);
+
+        let index = self.index as u32;
+        unsafe {
+            InitBgFromTemplate(&raw const template);
+            if tiles.1 != 0 {
+                LoadBgTiles(index, tiles.0.cast(), tiles.1 as _, self.base_tile);
+            }
+            SetBgTilemapBuffer(index, map.0.cast_mut().cast());
+            LoadBgTilemap(index, map.0.cast(), map.1 as _, 0);
+        }
+    }
+}
+
 pub struct Background<Set, Map> {
     index: BackgroundIndex,
     _own: (Set, Map),
@@ -300,38 +619,40 @@ pub struct Background<Set, Map> {
 
 impl<Set, Map> Background<Set, Map>
 where
-    Set: Buffer<TileBitmap4bpp>,
     Map: Buffer<Tile4bpp>,
 {
-    pub async fn load(
+    pub async fn load<B: TileBitmap>(
         index: BackgroundIndex,
         priority: u16,
         tileset: Tileset<Set>,
         tilemap: Tilemap<Map>,
-    ) -> Self {
-        let mut template: BgTemplate = BgTemplate::default();
-        template.set_bg(index as u16);
-        template.set_charBaseIndex(tileset.char_base);
-        template.set_mapBaseIndex(tilemap.map);
-        template.set_baseTile(tileset.offset);
-        template.set_paletteMode(0);
-        template.set_priority(priority);
-        template.set_screenSize(0);
-
-        unsafe { InitBgFromTemplate(&raw const template) };
-        let tiles = &tileset.tiles;
-        unsafe {
-            LoadBgTiles(
-                index as u32,
-                tiles.get().as_ptr().cast(),
-                tiles.size_bytes() as _,
-                tileset.offset,
-            )
-        };
-
+    ) -> Self
+    where
+        Set: Buffer<B>,
+    {
         let map = &tilemap.buffer;
-        unsafe { SetBgTilemapBuffer(index as _, map.as_ptr().cast_mut().cast()) };
-        unsafe { LoadBgTilemap(index as _, map.as_ptr().cast(), map.size_bytes() as _, 0) };
+        if map.size_bytes() < tilemap.size.tilemap_bytes() {
+            panic!(
+                "Tilemap too small for {:?}: {} bytes",
+                tilemap.size,
+                map.size_bytes()
+            );
+        }
+
+        let layout = BgLayout {
+            index,
+            priority,
+            char_base: tileset.char_base,
+            map_base: tilemap.map,
+            base_tile: tileset.offset,
+            palette_mode: B::PALETTE_MODE,
+            screen_size: tilemap.size as u16,
+        };
+        let tiles = &tileset.tiles;
+        layout.init(
+            (tiles.as_ptr(), tiles.size_bytes()),
+            (map.as_ptr(), tilemap.size.tilemap_bytes()),
+        );
         sleep(1).await;
 
         Background {
@@ -345,6 +666,189 @@ where
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct AffineTransform {
+    /// Point of the background shown at `display_center`, in 1/256th of a pixel
+    pub source_center: Vec2D<i32>,
+    pub display_center: Vec2D<i16>,
+    /// 8.8 fixed point, 0x100 is the original size
+    pub scale: Vec2D<i16>,
+    /// 0x10000 is a full turn
+    pub rotation: u16,
+}
+
+impl AffineTransform {
+    pub const IDENTITY: Self = AffineTransform {
+        source_center: Vec2D::new(0, 0),
+        display_center: Vec2D::new(0, 0),
+        scale: Vec2D::new(0x100, 0x100),
+        rotation: 0,
+    };
+}
+
+/// Affine background, always drawn on background 2 since `SetBgAffine` only drives its registers
+pub struct AffineBackground<Set, Map> {
+    size: AffineSize,
+    /// Raw mode the background replaced, restored on drop
+    previous_mode: u32,
+    _own: (Set, Map),
+}
+
+impl<Set, Map> AffineBackground<Set, Map>
+where
+    Set: Buffer<TileBitmap8bpp>,
+    Map: Buffer<TileAffine>,
+{
+    pub async fn load(
+        mode: BgMode,
+        priority: u16,
+        tileset: Tileset<Set>,
+        tilemap: AffineTilemap<Map>,
+    ) -> Self {
+        if mode == BgMode::Text {
+            panic!("Affine backgrounds need an affine bg mode");
+        }
+        let map = &tilemap.buffer;
+        if map.size_bytes() < tilemap.size.tilemap_bytes() {
+            panic!(
+                "Tilemap too small for {:?}: {} bytes",
+                tilemap.size,
+                map.size_bytes()
+            );
+        }
+
+        let previous_mode = unsafe { GetBgMode() };
+        set_bg_mode(mode);
+        let layout = BgLayout {
+            index: BackgroundIndex::Background2,
+            priority,
+            char_base: tileset.char_base,
+            map_base: tilemap.map,
+            base_tile: tileset.offset,
+            palette_mode: TileBitmap8bpp::PALETTE_MODE,
+            screen_size: tilemap.size as u16,
+        };
+        let tiles = &tileset.tiles;
+        layout.init(
+            (tiles.as_ptr(), tiles.size_bytes()),
+            (map.as_ptr(), tilemap.size.tilemap_bytes()),
+        );
+        let background = AffineBackground {
+            size: tilemap.size,
+            previous_mode,
+            _own: (tileset.tiles, tilemap.buffer),
+        };
+        background.set_transform(AffineTransform::IDENTITY);
+        sleep(1).await;
+        background
+    }
+
+    pub fn handle(&self) -> AffineBgHandle<'_> {
+        AffineBgHandle {
+            size: self.size,
+            _bg: PhantomData,
+        }
+    }
+
+    pub fn set_transform(&self, transform: AffineTransform) {
+        let AffineTransform {
+            source_center,
+            display_center,
+            scale,
+            rotation,
+        } = transform;
+        unsafe {
+            SetBgAffine(
+                BackgroundIndex::Background2 as u32,
+                source_center.x,
+                source_center.y,
+                display_center.x,
+                display_center.y,
+                scale.x,
+                scale.y,
+                rotation,
+            )
+        };
+    }
+}
+
+impl<Set, Map> Drop for AffineBackground<Set, Map> {
+    fn drop(&mut self) {
+        let bg = BackgroundIndex::Background2;
+        *DIRTY_TILEMAPS.borrow_mut() &= !(1 << bg as u8);
+        unsafe {
+            UnsetBgTilemapBuffer(bg as u32);
+            HideBg(bg as u32);
+            SetBgMode(self.previous_mode);
+        }
+    }
+}
+
+/// Handle to the affine background, whose tilemap has a byte per tile
+#[derive(Debug, Clone, Copy)]
+pub struct AffineBgHandle<'a> {
+    size: AffineSize,
+    _bg: PhantomData<&'a ()>,
+}
+
+impl AffineBgHandle<'_> {
+    /// Shared with text backgrounds, for everything but the tilemap entries
+    fn bg(&self) -> BgHandle<'_> {
+        BgHandle(BackgroundIndex::Background2, PhantomData)
+    }
+
+    pub fn show(&self) {
+        self.bg().show();
+    }
+
+    pub fn copy_tilemap_to_vram(&self) {
+        self.bg().copy_tilemap_to_vram();
+    }
+
+    pub fn schedule_copy_tilemap(&self) {
+        self.bg().schedule_copy_tilemap();
+    }
+
+    /// Schedules the vram copy only if the tilemap buffer was edited
+    pub fn flush(&self) {
+        self.bg().flush();
+    }
+
+    fn tilemap_entry(&self, pos: Vec2D<u8>) -> *mut TileAffine {
+        let tiles = self.size.tiles();
+        if pos.x as u16 >= tiles || pos.y as u16 >= tiles {
+            panic!("Tile {pos:?} outside of the {tiles}x{tiles} affine tilemap");
+        }
+        unsafe {
+            let buffer: *mut TileAffine =
+                GetBgTilemapBuffer(BackgroundIndex::Background2 as u32).cast();
+            if buffer.is_null() {
+                panic!("Affine background has no tilemap buffer");
+            }
+            buffer.add(pos.y as usize * tiles as usize + pos.x as usize)
+        }
+    }
+
+    pub fn get_tile(&self, pos: Vec2D<u8>) -> TileAffine {
+        unsafe { *self.tilemap_entry(pos) }
+    }
+
+    pub fn set_tile(&self, pos: Vec2D<u8>, tile: TileAffine) {
+        let entry = unsafe { &mut *self.tilemap_entry(pos) };
+        if *entry != tile {
+            *entry = tile;
+            self.bg().mark_dirty();
+        }
+    }
+
+    /// Replaces every tile in `rect` with the result of `f`
+    pub fn map_rect(&self, rect: Rect<u8>, mut f: impl FnMut(Vec2D<u8>, TileAffine) -> TileAffine) {
+        for pos in rect.positions() {
+            self.set_tile(pos, f(pos, self.get_tile(pos)));
+        }
+    }
+}
+
 pub struct SpriteHandle {
     sprite_index: u16,
 }
@@ -353,6 +857,66 @@ unsafe fn sprite_at<'a>(index: usize) -> &'a mut pokeemerald::Sprite {
     unsafe { &mut static_deref(&raw mut gSprites)[index] }
 }
 
+/// Per-frame sprite logic run by `AnimateSprites`, in place of the sprite's C callback
+pub trait SpriteBehavior {
+    fn update(&mut self, sprite: &mut pokeemerald::Sprite);
+}
+
+impl<F: FnMut(&mut pokeemerald::Sprite)> SpriteBehavior for F {
+    fn update(&mut self, sprite: &mut pokeemerald::Sprite) {
+        self(sprite)
+    }
+}
+
+struct AttachedBehavior {
+    /// Taken out while it runs
+    behavior: Option<Box<dyn SpriteBehavior>>,
+    previous: SpriteCallback,
+}
+
+static SPRITE_BEHAVIORS: RefCellSync<[Option<AttachedBehavior>; MAX_SPRITES as usize]> =
+    RefCellSync::new([const { None }; MAX_SPRITES as usize]);
+
+extern "C" fn sprite_behavior_callback(sprite: *mut pokeemerald::Sprite) {
+    let sprites = unsafe { static_deref(&raw mut gSprites) };
+    let index = unsafe { sprite.offset_from(sprites.as_ptr()) } as usize;
+
+    // Only the behavior is taken out while running, so it can touch the table, e.g. by
+    // replacing or clearing itself, and its slot tells whether it did
+    let behavior = SPRITE_BEHAVIORS.borrow_mut()[index]
+        .as_mut()
+        .and_then(|attached| attached.behavior.take());
+    let Some(mut behavior) = behavior else {
+        return;
+    };
+    behavior.update(unsafe { &mut *sprite });
+
+    let previous = {
+        let mut behaviors = SPRITE_BEHAVIORS.borrow_mut();
+        match &mut behaviors[index] {
+            Some(attached) if attached.behavior.is_none() => {
+                attached.behavior = Some(behavior);
+                attached.previous
+            }
+            // Replaced or cleared by `update`
+            _ => return,
+        }
+    };
+
+    // The sprite's own callback keeps running after it, e.g. the mon icon animation,
+    // and may move on to another one
+    let Some(previous) = previous else {
+        return;
+    };
+    let sprite = unsafe { &mut *sprite };
+    sprite.callback = Some(previous);
+    unsafe { previous(sprite) };
+    if let Some(attached) = SPRITE_BEHAVIORS.borrow_mut()[index].as_mut() {
+        attached.previous = sprite.callback;
+        sprite.callback = Some(sprite_behavior_callback);
+    }
+}
+
 impl SpriteHandle {
     pub fn set_pos(&self, pos: Vec2D<i16>) {
         let sprite = unsafe { sprite_at(self.sprite_index as usize) };
@@ -399,6 +963,51 @@ impl SpriteHandle {
             RequestSpriteFrameImageCopy(0, sprite.oam.tileNum(), sprite.images);
         }
     }
+
+    pub fn data(&self, index: usize) -> i16 {
+        let sprite = unsafe { sprite_at(self.sprite_index as usize) };
+        sprite.data[index]
+    }
+    pub fn set_data(&self, index: usize, value: i16) {
+        let sprite = unsafe { sprite_at(self.sprite_index as usize) };
+        sprite.data[index] = value;
+    }
+
+    /// Runs `behavior` every frame until cleared or until the owning sprite is dropped,
+    /// followed by the sprite's own callback
+    pub fn set_behavior(&self, behavior: impl SpriteBehavior + 'static) {
+        let index = self.sprite_index as usize;
+        let sprite = unsafe { sprite_at(index) };
+        let mut behaviors = SPRITE_BEHAVIORS.borrow_mut();
+        let previous = match behaviors[index].take() {
+            Some(attached) => attached.previous,
+            None => sprite.callback,
+        };
+        behaviors[index] = Some(AttachedBehavior {
+            behavior: Some(Box::new(behavior)),
+            previous,
+        });
+        sprite.callback = Some(sprite_behavior_callback);
+    }
+
+    /// Detaches the behavior and restores the callback the sprite had before it
+    pub fn clear_behavior(&self) {
+        let index = self.sprite_index as usize;
+        let attached = SPRITE_BEHAVIORS.borrow_mut()[index].take();
+        if let Some(attached) = attached {
+            let sprite = unsafe { sprite_at(index) };
+            sprite.callback = attached.previous;
+        }
+    }
+
+    fn has_behavior(&self) -> bool {
+        SPRITE_BEHAVIORS.borrow()[self.sprite_index as usize].is_some()
+    }
+
+    fn drop_behavior(&self) {
+        let behavior = SPRITE_BEHAVIORS.borrow_mut()[self.sprite_index as usize].take();
+        drop(behavior);
+    }
 }
 
 pub struct SpriteSheet<B> {
@@ -410,6 +1019,11 @@ pub struct SpriteSheet<B> {
 
 impl<B: Buffer<TileBitmap4bpp>> SpriteSheet<B> {
     pub fn load(buffer: B, tag: u16, size: u8) -> Self {
+        Self::try_load(buffer, tag, size)
+            .unwrap_or_else(|err| panic!("Couldn't load sprite sheet: {err:?}"))
+    }
+
+    pub fn try_load(buffer: B, tag: u16, size: u8) -> Result<Self, OutOfMemory> {
         let sheet = pokeemerald::SpriteSheet {
             data: buffer.as_ptr().cast(),
             size: buffer.size_bytes() as u16,
@@ -417,14 +1031,14 @@ impl<B: Buffer<TileBitmap4bpp>> SpriteSheet<B> {
         };
         let index = unsafe { LoadSpriteSheet(&raw const sheet) };
         if index == 0xFFFF {
-            panic!("Couldn't load sprite")
+            return Err(OutOfMemory::SpriteTiles);
         }
-        SpriteSheet {
+        Ok(SpriteSheet {
             _own: buffer,
             _tilestart: index,
             tag,
             size,
-        }
+        })
     }
 }
 
@@ -449,12 +1063,183 @@ pub static DUMMY_SPRITE_ANIMS: SpriteAnims = unsafe {
 
 #[derive(Clone, Copy, Debug)]
 pub enum Anim {
-    Frame(AnimFrameCmd),
-    Loop { count: u32 },
-    Jump { target: u32 },
+    Frame {
+        value: u16,
+        duration: u8,
+        hflip: bool,
+        vflip: bool,
+    },
+    Loop {
+        count: u8,
+    },
+    Jump {
+        target: u8,
+    },
     End,
 }
 
+impl Anim {
+    pub const fn frame(value: u16, duration: u8) -> Anim {
+        Anim::Frame {
+            value,
+            duration,
+            hflip: false,
+            vflip: false,
+        }
+    }
+
+    pub const fn compile(self) -> AnimCmd {
+        match self {
+            Anim::Frame {
+                value,
+                duration,
+                hflip,
+                vflip,
+            } => anim_frame(value, duration, hflip, vflip),
+            Anim::Loop { count } => anim_loop(count),
+            Anim::Jump { target } => anim_jump(target),
+            Anim::End => anim_end(),
+        }
+    }
+}
+
+const ANIM_MAX_FIELD: u8 = 0b0011_1111;
+
+/// Turns a sequence into `AnimCmd`s, panicking at compile time on sequences the engine can't run
+pub const fn compile_anim<const N: usize>(anims: [Anim; N]) -> [AnimCmd; N] {
+    let mut cmds = [anim_end(); N];
+    let mut index = 0;
+    while index < N {
+        match anims[index] {
+            Anim::Frame {
+                value, duration, ..
+            } => {
+                if value >= 0xFFFD {
+                    panic!("Frame value collides with a command type");
+                }
+                if duration > ANIM_MAX_FIELD {
+                    panic!("Frame duration is limited to 63");
+                }
+            }
+            Anim::Loop { count } => {
+                if count > ANIM_MAX_FIELD {
+                    panic!("Loop count is limited to 63");
+                }
+                if index == 0 {
+                    panic!("Loop needs frames before it");
+                }
+            }
+            Anim::Jump { target } => {
+                if target > ANIM_MAX_FIELD {
+                    panic!("Jump target is limited to 63");
+                }
+                if target as usize >= N {
+                    panic!("Jump target out of the sequence");
+                }
+                if target as usize == index {
+                    panic!("Jump to itself never shows a frame");
+                }
+            }
+            Anim::End => {}
+        }
+        cmds[index] = anims[index].compile();
+        index += 1;
+    }
+    match N {
+        0 => panic!("Empty animation"),
+        _ => match anims[N - 1] {
+            Anim::End | Anim::Jump { .. } => {}
+            _ => panic!("Animation must finish with End or Jump"),
+        },
+    }
+    cmds
+}
+
+/// One single-frame animation per sheet offset, animation `i` showing tile `i * stride`
+pub const fn tile_offset_anims<const N: usize>(stride: u16) -> [[AnimCmd; 2]; N] {
+    let mut anims = [[anim_end(); 2]; N];
+    let mut index = 0;
+    while index < N {
+        anims[index] = compile_anim([Anim::frame(index as u16 * stride, 0), Anim::End]);
+        index += 1;
+    }
+    anims
+}
+
+pub const fn anim_pointers<const N: usize, const L: usize>(
+    anims: &[[AnimCmd; L]; N],
+) -> [*const AnimCmd; N] {
+    let mut pointers = [null(); N];
+    let mut index = 0;
+    while index < N {
+        pointers[index] = anims[index].as_ptr();
+        index += 1;
+    }
+    pointers
+}
+
+#[derive(Clone, Copy)]
+pub struct AnimTable(&'static [*const AnimCmd]);
+unsafe impl Sync for AnimTable {}
+
+impl AnimTable {
+    pub const fn new(anims: &'static [*const AnimCmd]) -> Self {
+        AnimTable(anims)
+    }
+
+    pub const fn as_ptr(&self) -> *const *const AnimCmd {
+        self.0.as_ptr()
+    }
+
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn sprite_anims(&self) -> SpriteAnims {
+        SpriteAnims {
+            anims: self.as_ptr(),
+            affine_anims: DUMMY_SPRITE_ANIMS.affine_anims,
+        }
+    }
+}
+
+/// Builds a static [`AnimTable`], either from explicit [`Anim`] sequences or
+/// as one single-frame animation per tile offset
+///
+/// ```ignore
+/// anim_table!(static ICON_ANIMS = [
+///     [Anim::frame(0, 6), Anim::frame(1, 6), Anim::Jump { target: 0 }],
+///     [Anim::frame(0, 8), Anim::Loop { count: 3 }, Anim::End],
+/// ]);
+/// anim_table!(static TYPE_ANIMS = tile_offsets(23, 4));
+/// ```
+#[macro_export]
+macro_rules! anim_table {
+    ($vis:vis static $name:ident = tile_offsets($count:expr, $stride:expr)) => {
+        $vis static $name: $crate::graphics::AnimTable = {
+            use $crate::graphics::*;
+            use $crate::pokeemerald::AnimCmd;
+            const ANIMS: &[[AnimCmd; 2]; $count] = &tile_offset_anims::<{ $count }>($stride);
+            const POINTERS: &[*const AnimCmd; $count] = &anim_pointers(ANIMS);
+            AnimTable::new(POINTERS)
+        };
+    };
+    ($vis:vis static $name:ident = [$([$($anim:expr),* $(,)?]),* $(,)?]) => {
+        $vis static $name: $crate::graphics::AnimTable = {
+            use $crate::graphics::*;
+            use $crate::pokeemerald::AnimCmd;
+            AnimTable::new(&[$({
+                const SEQUENCE: &[AnimCmd] = &compile_anim([$($anim),*]);
+                SEQUENCE.as_ptr()
+            }),*])
+        };
+    };
+}
+
 pub const fn anim_frame(value: u16, duration: u8, hflip: bool, vflip: bool) -> AnimCmd {
     let [a, b] = value.to_le_bytes();
     let mut c = duration & 0b0011_1111;
@@ -471,6 +1256,24 @@ pub const fn anim_frame(value: u16, duration: u8, hflip: bool, vflip: bool) -> A
     AnimCmd { frame: cmd }
 }
 
+pub const fn anim_loop(count: u8) -> AnimCmd {
+    let [a, b] = (-3i16).to_le_bytes();
+    let cmd = AnimLoopCmd {
+        _bitfield_align_1: [],
+        _bitfield_1: __BindgenBitfieldUnit::new([a, b, count & ANIM_MAX_FIELD]),
+    };
+    AnimCmd { loop_: cmd }
+}
+
+pub const fn anim_jump(target: u8) -> AnimCmd {
+    let [a, b] = (-2i16).to_le_bytes();
+    let cmd = AnimJumpCmd {
+        _bitfield_align_1: [],
+        _bitfield_1: __BindgenBitfieldUnit::new([a, b, target & ANIM_MAX_FIELD]),
+    };
+    AnimCmd { jump: cmd }
+}
+
 pub const fn anim_end() -> AnimCmd {
     AnimCmd { type_: -1 }
 }
@@ -487,6 +1290,15 @@ pub struct SheetSprite<'a> {
 
 impl<'a> SheetSprite<'a> {
     pub fn load(sheet: &'a SpriteSheet<impl Any>, anims: SpriteAnims, palette: ObjPalette) -> Self {
+        Self::try_load(sheet, anims, palette)
+            .unwrap_or_else(|err| panic!("Couldn't load sprite: {err:?}"))
+    }
+
+    pub fn try_load(
+        sheet: &'a SpriteSheet<impl Any>,
+        anims: SpriteAnims,
+        palette: ObjPalette,
+    ) -> Result<Self, OutOfMemory> {
         let size = sheet.size as u32;
         let mut oam = OamData::default();
         oam.set_size((size >> 2) & 0b11);
@@ -504,14 +1316,17 @@ impl<'a> SheetSprite<'a> {
             ..Default::default()
         };
         let sprite_index = unsafe { CreateSprite(&raw const template, 0, 0, 0) };
+        if sprite_index as u32 == MAX_SPRITES {
+            return Err(OutOfMemory::Sprites);
+        }
         let handle = SpriteHandle {
             sprite_index: sprite_index as u16,
         };
         handle.set_palette(palette.index as u16);
-        SheetSprite {
+        Ok(SheetSprite {
             handle,
             _own: PhantomData,
-        }
+        })
     }
 }
 
@@ -524,6 +1339,7 @@ impl Deref for SheetSprite<'_> {
 
 impl Drop for SheetSprite<'_> {
     fn drop(&mut self) {
+        self.handle.drop_behavior();
         unsafe {
             DestroySprite(sprite_at(self.handle.sprite_index as usize));
         }
@@ -537,12 +1353,22 @@ pub struct Sprite<Img: Buffer<TileBitmap4bpp>> {
 
 impl<Img: Buffer<TileBitmap4bpp>> Sprite<Img> {
     pub async fn load(image: SpriteImage<Img>, anims: SpriteAnims, palette: ObjPalette) -> Self {
+        Self::try_load(image, anims, palette)
+            .await
+            .unwrap_or_else(|err| panic!("Couldn't load sprite: {err:?}"))
+    }
+
+    pub async fn try_load(
+        image: SpriteImage<Img>,
+        anims: SpriteAnims,
+        palette: ObjPalette,
+    ) -> Result<Self, OutOfMemory> {
         let frame = SpriteFrameImage {
             data: image.buf.as_ptr().cast(),
             size: image.buf.size_bytes() as _,
             relativeFrames: 0,
         };
-        let frame = Box::new(frame);
+        let frame = try_box(frame)?;
 
         let mut oam = OamData::default();
         oam.set_size((image.size >> 2) & 0b11);
@@ -561,20 +1387,23 @@ impl<Img: Buffer<TileBitmap4bpp>> Sprite<Img> {
         };
 
         let sprite_index = unsafe { CreateSprite(&raw const template, 0, 0, 0) };
+        if sprite_index as u32 == MAX_SPRITES {
+            return Err(OutOfMemory::Sprites);
+        }
         let sprite = SpriteHandle {
             sprite_index: sprite_index as _,
         };
         sprite.set_palette(palette.index as u16);
         sprite.request_copy();
-        Sprite {
+        Ok(Sprite {
             _own: (palette, image.buf, frame),
             sprite,
-        }
+        })
     }
 
     #[allow(unused)]
     pub fn debug(&self) {
-        mgba_warn!("{:?} {:?}", self._own.1.as_ptr(), &raw const *self._own.2);
+        trace!("{:?} {:?}", self._own.1.as_ptr(), &raw const *self._own.2);
     }
 
     pub fn handle(&self) -> &SpriteHandle {
@@ -584,51 +1413,449 @@ impl<Img: Buffer<TileBitmap4bpp>> Sprite<Img> {
 
 impl<T: Buffer<TileBitmap4bpp>> Drop for Sprite<T> {
     fn drop(&mut self) {
+        self.sprite.drop_behavior();
         unsafe {
             DestroySpriteAndFreeResources(sprite_at(self.sprite.sprite_index as _));
         }
     }
 }
 
-pub struct PokemonSpritePic {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PicSide {
+    Front,
+    Back,
+}
+
+/// Returned by the pic sprite functions when they couldn't create one
+const PIC_SPRITE_NONE: u16 = 0xFFFF;
+
+/// What a pic sprite with `frames_bytes` of frames most likely failed on, as
+/// the C side returns the same value for a full heap and full sprite pools
+fn pic_sprite_error(frames_bytes: usize) -> OutOfMemory {
+    match heap_stats().largest_free < frames_bytes {
+        true => OutOfMemory::Heap(frames_bytes),
+        false => OutOfMemory::Sprites,
+    }
+}
+
+/// Side of a [`PokemonSpritePic`], as a type so only front pics can play the front animation
+pub trait PicSideType {
+    const SIDE: PicSide;
+}
+
+pub enum Front {}
+pub enum Back {}
+
+impl PicSideType for Front {
+    const SIDE: PicSide = PicSide::Front;
+}
+impl PicSideType for Back {
+    const SIDE: PicSide = PicSide::Back;
+}
+
+pub type FrontPic = PokemonSpritePic<Front>;
+pub type BackPic = PokemonSpritePic<Back>;
+
+pub struct PokemonSpritePic<Side: PicSideType = Front> {
     sprite: SpriteHandle,
+    species: u16,
+    /// Task running the front animation, destroyed with the sprite it points to
+    anim_task: Cell<Option<u8>>,
+    _side: PhantomData<Side>,
+}
+
+impl PokemonSpritePic<Front> {
+    pub fn new(poke: &data::Pokemon, slot: u8) -> Self {
+        Self::try_new(poke, slot).unwrap_or_else(|err| panic!("Couldn't load sprite: {err:?}"))
+    }
+
+    pub fn try_new(poke: &data::Pokemon, slot: u8) -> Result<Self, OutOfMemory> {
+        Self::create(poke, slot)
+    }
+
+    /// Plays the cry if asked, then the species' front animation after its delay
+    ///
+    /// The animation is a task, so it only runs in scenes calling `RunTasks`
+    /// every frame. It takes over the sprite's callback, so the sprite can't
+    /// have a [`SpriteBehavior`] at the same time.
+    pub async fn play_front_anim(&self, cry: bool) {
+        if self.sprite.has_behavior() {
+            panic!("A front animation would detach the sprite's behavior");
+        }
+        let info = data::get_species(self.species as usize);
+        if cry {
+            self.play_cry();
+            if unsafe { HasTwoFramesAnimation(self.species) } != 0 {
+                self.sprite.start_animation(1);
+            }
+        }
+        sleep(info.frontAnimDelay as usize).await;
+
+        self.stop_front_anim();
+        let active = || array::from_fn::<_, { NUM_TASKS as usize }, _>(|id| task_at(id).isActive);
+        let before = active();
+        let sprite = unsafe { sprite_at(self.sprite.sprite_index as usize) };
+        unsafe { LaunchAnimationTaskForFrontSprite(sprite, info.frontAnimId) };
+        let created = active()
+            .iter()
+            .zip(before)
+            .position(|(&after, before)| after != 0 && before == 0);
+        self.anim_task.set(created.map(|id| id as u8));
+    }
+}
+
+impl PokemonSpritePic<Back> {
+    pub fn new_back(poke: &data::Pokemon, slot: u8) -> Self {
+        Self::try_new_back(poke, slot).unwrap_or_else(|err| panic!("Couldn't load sprite: {err:?}"))
+    }
+
+    pub fn try_new_back(poke: &data::Pokemon, slot: u8) -> Result<Self, OutOfMemory> {
+        Self::create(poke, slot)
+    }
 }
 
-impl PokemonSpritePic {
-    pub fn new(poke: &data::Pokemon, slot: u8) -> PokemonSpritePic {
+impl<Side: PicSideType> PokemonSpritePic<Side> {
+    fn create(poke: &data::Pokemon, slot: u8) -> Result<Self, OutOfMemory> {
+        const FRAMES_BYTES: usize = 2 * 64 * 64 / 2;
         let species = poke.species();
         let personality = poke.personality();
         let shiny = poke.shiny();
+        let flags = match Side::SIDE {
+            PicSide::Front => MON_PIC_AFFINE_FRONT,
+            PicSide::Back => MON_PIC_AFFINE_BACK,
+        };
         unsafe {
             let sprite_index = CreateMonPicSprite_Affine(
                 species,
                 shiny as _,
                 personality,
-                MON_PIC_AFFINE_FRONT as _,
+                flags as _,
                 0,
                 0,
                 slot,
                 TAG_NONE as _,
             );
-            PokemonSpritePic {
-                sprite: SpriteHandle { sprite_index },
+            if sprite_index == PIC_SPRITE_NONE {
+                return Err(pic_sprite_error(FRAMES_BYTES));
             }
+            Ok(PokemonSpritePic {
+                sprite: SpriteHandle { sprite_index },
+                species,
+                anim_task: Cell::new(None),
+                _side: PhantomData,
+            })
         }
     }
 
     pub fn handle(&mut self) -> &mut SpriteHandle {
         &mut self.sprite
     }
+
+    pub fn play_cry(&self) {
+        play_cry(self.species);
+    }
+
+    /// Destroys the animation task if it's still running on this sprite
+    fn stop_front_anim(&self) {
+        let Some(id) = self.anim_task.take() else {
+            return;
+        };
+        // The task ends on its own with the animation, and its slot may be reused since
+        let task = task_at(id as usize);
+        let sprite = unsafe { sprite_at(self.sprite.sprite_index as usize) } as *mut _ as u32;
+        let target = (task.data[1] as u16 as u32) << 16 | task.data[2] as u16 as u32;
+        if task.isActive != 0 && target == sprite {
+            unsafe { DestroyTask(id) };
+        }
+    }
 }
 
-impl Drop for PokemonSpritePic {
+impl<Side: PicSideType> Drop for PokemonSpritePic<Side> {
     fn drop(&mut self) {
+        self.stop_front_anim();
+        self.sprite.drop_behavior();
         unsafe {
             FreeAndDestroyMonPicSprite(self.sprite.sprite_index);
         }
     }
 }
 
+fn task_at(id: usize) -> &'static Task {
+    if id >= NUM_TASKS as usize {
+        panic!("Task {id} out of bounds");
+    }
+    unsafe { &*static_index(&raw mut gTasks, id) }
+}
+
+pub fn play_cry(species: u16) {
+    unsafe { PlayCry_Normal(species, 0) };
+}
+
+pub struct TrainerSpritePic {
+    sprite: SpriteHandle,
+}
+
+impl TrainerSpritePic {
+    pub fn new(trainer_pic: u16, side: PicSide, slot: u8) -> TrainerSpritePic {
+        Self::try_new(trainer_pic, side, slot)
+            .unwrap_or_else(|err| panic!("Couldn't load sprite: {err:?}"))
+    }
+
+    pub fn try_new(
+        trainer_pic: u16,
+        side: PicSide,
+        slot: u8,
+    ) -> Result<TrainerSpritePic, OutOfMemory> {
+        const FRAMES_BYTES: usize = 4 * 64 * 64 / 2;
+        let front = side == PicSide::Front;
+        let sprite_index =
+            unsafe { CreateTrainerPicSprite(trainer_pic, front as _, 0, 0, slot, TAG_NONE as _) };
+        if sprite_index == PIC_SPRITE_NONE {
+            return Err(pic_sprite_error(FRAMES_BYTES));
+        }
+        Ok(TrainerSpritePic {
+            sprite: SpriteHandle { sprite_index },
+        })
+    }
+
+    pub fn handle(&mut self) -> &mut SpriteHandle {
+        &mut self.sprite
+    }
+}
+
+impl Drop for TrainerSpritePic {
+    fn drop(&mut self) {
+        self.sprite.drop_behavior();
+        unsafe {
+            FreeAndDestroyTrainerPicSprite(self.sprite.sprite_index);
+        }
+    }
+}
+
+/// The shared party/box icon palettes, icons borrow it so they can't outlive the palettes
+pub struct MonIconPalettes(());
+
+impl MonIconPalettes {
+    pub fn load() -> Self {
+        unsafe { LoadMonIconPalettes() };
+        MonIconPalettes(())
+    }
+}
+
+impl Drop for MonIconPalettes {
+    fn drop(&mut self) {
+        unsafe { FreeMonIconPalettes() };
+    }
+}
+
+/// Animation speeds of the icon's two-frame idle animation, matching the party menu HP states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonIconSpeed {
+    Fastest = 0,
+    Fast = 1,
+    Normal = 2,
+    Slow = 3,
+    Still = 4,
+}
+
+pub struct MonIcon<'a> {
+    sprite: SpriteHandle,
+    _palettes: PhantomData<&'a MonIconPalettes>,
+}
+
+impl<'a> MonIcon<'a> {
+    pub fn new(palettes: &'a MonIconPalettes, poke: &data::Pokemon, subpriority: u8) -> Self {
+        Self::try_new(palettes, poke, subpriority)
+            .unwrap_or_else(|err| panic!("Couldn't load sprite: {err:?}"))
+    }
+
+    /// Fails when all sprites are in use, or sprite vram can't fit the icon
+    pub fn try_new(
+        _palettes: &'a MonIconPalettes,
+        poke: &data::Pokemon,
+        subpriority: u8,
+    ) -> Result<Self, OutOfMemory> {
+        let species = poke.species();
+        let personality = poke.personality();
+        let sprite_index = unsafe {
+            CreateMonIcon(
+                species,
+                Some(SpriteCB_MonIcon),
+                0,
+                0,
+                subpriority,
+                personality,
+            )
+        };
+        if sprite_index as u32 == MAX_SPRITES {
+            return Err(OutOfMemory::Sprites);
+        }
+        Ok(MonIcon {
+            sprite: SpriteHandle {
+                sprite_index: sprite_index as u16,
+            },
+            _palettes: PhantomData,
+        })
+    }
+
+    pub fn set_speed(&self, speed: MonIconSpeed) {
+        unsafe {
+            let sprite = sprite_at(self.sprite.sprite_index as usize);
+            SetPartyHPBarSprite(sprite, speed as u8);
+        }
+    }
+}
+
+impl Deref for MonIcon<'_> {
+    type Target = SpriteHandle;
+    fn deref(&self) -> &Self::Target {
+        &self.sprite
+    }
+}
+
+impl Drop for MonIcon<'_> {
+    fn drop(&mut self) {
+        self.sprite.drop_behavior();
+        unsafe {
+            FreeAndDestroyMonIconSprite(sprite_at(self.sprite.sprite_index as usize));
+        }
+    }
+}
+
+/// Anything owning a sprite, so it can be placed in a [`SpriteGroup`]
+pub trait SpriteGroupMember {
+    fn sprite(&self) -> &SpriteHandle;
+}
+
+impl SpriteGroupMember for SheetSprite<'_> {
+    fn sprite(&self) -> &SpriteHandle {
+        &self.handle
+    }
+}
+impl<Img: Buffer<TileBitmap4bpp>> SpriteGroupMember for Sprite<Img> {
+    fn sprite(&self) -> &SpriteHandle {
+        &self.sprite
+    }
+}
+impl<Side: PicSideType> SpriteGroupMember for PokemonSpritePic<Side> {
+    fn sprite(&self) -> &SpriteHandle {
+        &self.sprite
+    }
+}
+impl SpriteGroupMember for TrainerSpritePic {
+    fn sprite(&self) -> &SpriteHandle {
+        &self.sprite
+    }
+}
+impl SpriteGroupMember for MonIcon<'_> {
+    fn sprite(&self) -> &SpriteHandle {
+        &self.sprite
+    }
+}
+
+struct GroupEntry<'a, K> {
+    key: K,
+    offset: Vec2D<i16>,
+    member: Box<dyn SpriteGroupMember + 'a>,
+}
+
+/// Sprites placed relative to a shared position, moved and shown as one element
+pub struct SpriteGroup<'a, K> {
+    pos: Vec2D<i16>,
+    entries: Vec<GroupEntry<'a, K>>,
+}
+
+impl<'a, K: PartialEq> SpriteGroup<'a, K> {
+    pub fn new(pos: Vec2D<i16>) -> Self {
+        SpriteGroup {
+            pos,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds `member` at `offset` from the group position, replacing any member with the same key
+    pub fn insert(&mut self, key: K, member: impl SpriteGroupMember + 'a, offset: Vec2D<i16>) {
+        self.remove(&key);
+        member.sprite().set_pos(self.pos + offset);
+        self.entries.push(GroupEntry {
+            key,
+            offset,
+            member: Box::new(member),
+        });
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.key != *key);
+        len != self.entries.len()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&SpriteHandle> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == *key)
+            .map(|entry| entry.member.sprite())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn sprites(&self) -> impl Iterator<Item = &SpriteHandle> {
+        self.entries.iter().map(|entry| entry.member.sprite())
+    }
+
+    pub fn pos(&self) -> Vec2D<i16> {
+        self.pos
+    }
+
+    pub fn set_pos(&mut self, pos: Vec2D<i16>) {
+        self.pos = pos;
+        for entry in &self.entries {
+            entry.member.sprite().set_pos(pos + entry.offset);
+        }
+    }
+
+    pub fn set_invisible(&self, invisible: bool) {
+        self.sprites()
+            .for_each(|sprite| sprite.set_invisible(invisible));
+    }
+
+    pub fn set_priority(&self, priority: u8) {
+        self.sprites()
+            .for_each(|sprite| sprite.set_priority(priority));
+    }
+
+    pub fn set_palette(&self, palette: ObjPalette) {
+        self.sprites()
+            .for_each(|sprite| sprite.set_palette(palette.index as u16));
+    }
+
+    /// Slides the group to `target` over `frames` frames
+    pub async fn move_to(&mut self, target: Vec2D<i16>, frames: i16) {
+        let start = self.pos;
+        for i in 1..=frames {
+            self.set_pos(target * i / frames + start * (frames - i) / frames);
+            sleep(1).await;
+        }
+        self.set_pos(target);
+    }
+
+    /// Slides both groups to each other's position, then swaps them so each
+    /// group ends up back at its own position holding the other's sprites
+    pub async fn swap(&mut self, other: &mut Self, frames: i16) {
+        let (pos_self, pos_other) = (self.pos, other.pos);
+        for i in 1..=frames {
+            self.set_pos(pos_other * i / frames + pos_self * (frames - i) / frames);
+            other.set_pos(pos_self * i / frames + pos_other * (frames - i) / frames);
+            sleep(1).await;
+        }
+        self.set_pos(pos_other);
+        other.set_pos(pos_self);
+        core::mem::swap(self, other);
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Font {
     pub font: u8,
@@ -743,21 +1970,26 @@ impl WindowHandle {
             )
         };
     }
+
     pub fn copy_tilemap(&self, tileset: &[TileBitmap4bpp], tilemap: &[TilePlain], rect: Rect<u8>) {
-        let mut buffer = Vec::with_capacity(
-            rect.width as usize * rect.height as usize * size_of::<TileBitmap4bpp>(),
-        );
-        for y in 0..rect.height {
-            for x in 0..rect.width {
-                let offset = y * rect.width + x;
-                let tile = tilemap[offset as usize].0;
-                buffer.extend_from_slice(&tileset[tile as usize].0);
-            }
-        }
+        let tiles = rect.width as usize * rect.height as usize;
+        let mut buffer = vec![0; tiles * size_of::<TileBitmap4bpp>()];
+        gather_tiles(tileset, &tilemap[..tiles], &mut buffer);
         self.blit_bitmap(&buffer, rect.tile_to_pixel());
     }
 }
 
+crate::iwram! {
+    /// Copies the bitmap of each tile of `tilemap` one after the other into `out`
+    ///
+    /// The hot loop of [`WindowHandle::copy_tilemap`], a leaf so it runs from IWRAM.
+    fn gather_tiles(tileset: &[TileBitmap4bpp], tilemap: &[TilePlain], out: &mut [u8]) {
+        for (dest, tile) in out.as_chunks_mut().0.iter_mut().zip(tilemap) {
+            *dest = tileset[tile.0 as usize].0;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Window {
     handle: WindowHandle,
@@ -772,6 +2004,17 @@ impl Deref for Window {
 
 impl Window {
     pub fn create(bg: BgHandle<'_>, rect: Rect<u8>, palette: BgPalette, base_block: u16) -> Window {
+        Self::try_create(bg, rect, palette, base_block)
+            .unwrap_or_else(|err| panic!("Couldn't create window: {err:?}"))
+    }
+
+    /// Fails when all windows are in use, or `gHeap` can't fit the window's tiles
+    pub fn try_create(
+        bg: BgHandle<'_>,
+        rect: Rect<u8>,
+        palette: BgPalette,
+        base_block: u16,
+    ) -> Result<Window, OutOfMemory> {
         let window_template = WindowTemplate {
             baseBlock: base_block,
             bg: bg.0 as _,
@@ -783,11 +2026,11 @@ impl Window {
         };
         let index = unsafe { AddWindow(&raw const window_template) };
         if index == WINDOW_NONE {
-            panic!("WINDOW ALLOC FAILED")
+            return Err(OutOfMemory::Windows);
         }
-        mgba_warn!("Init window {}", index);
+        debug!("Init window {}", index);
         let handle = WindowHandle { index: index as u8 };
-        Window { handle }
+        Ok(Window { handle })
     }
 
     pub unsafe fn clear_all() {
@@ -808,6 +2051,158 @@ impl Drop for Window {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct GaugeColor {
+    pub fill: u8,
+    pub shadow: u8,
+}
+
+impl GaugeColor {
+    pub const fn new(fill: u8, shadow: u8) -> Self {
+        GaugeColor { fill, shadow }
+    }
+}
+
+/// Color used while the filled part of the gauge is below `percent` of its width
+#[derive(Clone, Copy, Debug)]
+pub struct GaugeThreshold {
+    pub percent: u8,
+    pub color: GaugeColor,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum GaugeStyle {
+    /// Whole height drawn with the fill color
+    Flat,
+    /// First `highlight` rows drawn with the fill color, the rest with the shadow color
+    Shaded { highlight: u16 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Gauge<'a> {
+    pub rect: Rect<u16>,
+    pub style: GaugeStyle,
+    pub empty: GaugeColor,
+    /// Checked in order, the last entry is used when no threshold matches
+    pub thresholds: &'a [GaugeThreshold],
+    value: u16,
+    max: u16,
+}
+
+impl<'a> Gauge<'a> {
+    pub const fn new(
+        rect: Rect<u16>,
+        style: GaugeStyle,
+        empty: GaugeColor,
+        thresholds: &'a [GaugeThreshold],
+    ) -> Self {
+        Gauge {
+            rect,
+            style,
+            empty,
+            thresholds,
+            value: 0,
+            max: 1,
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        self.value
+    }
+
+    pub fn set(&mut self, value: u16, max: u16) {
+        self.max = max.max(1);
+        self.value = value.min(self.max);
+    }
+
+    fn pixels_for(&self, value: u16) -> u16 {
+        (value as u32 * self.rect.width as u32 / self.max as u32) as u16
+    }
+
+    /// Smallest value that fills at least `pixels`
+    fn value_for(&self, pixels: u16) -> u16 {
+        let width = self.rect.width.max(1) as u32;
+        let value = (pixels as u32 * self.max as u32).div_ceil(width);
+        min(value, self.max as u32) as u16
+    }
+
+    fn color_for(&self, pixels: u16) -> GaugeColor {
+        let percent = pixels as u32 * 100 / self.rect.width.max(1) as u32;
+        self.thresholds
+            .iter()
+            .find(|threshold| percent < threshold.percent as u32)
+            .or(self.thresholds.last())
+            .map_or(self.empty, |threshold| threshold.color)
+    }
+
+    fn draw_part(&self, window: &WindowHandle, x: u16, width: u16, color: GaugeColor) {
+        if width == 0 {
+            return;
+        }
+        let rect = Rect {
+            x: self.rect.x + x,
+            width,
+            ..self.rect
+        };
+        match self.style {
+            GaugeStyle::Flat => window.fill_rect(color.fill, rect),
+            GaugeStyle::Shaded { highlight } => {
+                let highlight = highlight.min(rect.height);
+                window.fill_rect(
+                    color.fill,
+                    Rect {
+                        height: highlight,
+                        ..rect
+                    },
+                );
+                window.fill_rect(
+                    color.shadow,
+                    Rect {
+                        y: rect.y + highlight,
+                        height: rect.height - highlight,
+                        ..rect
+                    },
+                );
+            }
+        }
+    }
+
+    fn draw_pixels(&self, window: &WindowHandle, pixels: u16) {
+        let pixels = pixels.min(self.rect.width);
+        self.draw_part(window, 0, pixels, self.color_for(pixels));
+        self.draw_part(window, pixels, self.rect.width - pixels, self.empty);
+    }
+
+    /// Draws the gauge into the window buffer, the caller is responsible for the vram copy
+    pub fn draw(&self, window: &WindowHandle) {
+        self.draw_pixels(window, self.pixels_for(self.value));
+    }
+
+    /// Drains or fills the gauge towards `value`, moving by `step` pixels every frame
+    ///
+    /// The value follows what's drawn, so it stays in sync if this is dropped midway.
+    pub async fn animate_to(&mut self, window: &WindowHandle, value: u16, step: u16) {
+        let value = value.min(self.max);
+        let target = self.pixels_for(value);
+        let mut pixels = self.pixels_for(self.value);
+        let step = step.max(1);
+        while pixels != target {
+            pixels = match pixels < target {
+                true => min(pixels + step, target),
+                false => pixels.saturating_sub(step).max(target),
+            };
+            self.value = match pixels == target {
+                true => value,
+                false => self.value_for(pixels),
+            };
+            self.draw(window);
+            window.copy_to_vram();
+            sleep(1).await;
+        }
+        self.value = value;
+    }
+}
+
 pub struct ListMenu<'a> {
     index: u8,
     _w: PhantomData<&'a ()>,
@@ -0,0 +1,258 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::Cell;
+use core::ffi::{CStr, c_char};
+use core::fmt;
+
+use arrayvec::ArrayVec;
+
+use crate::pokeemerald::{
+    Alloc_, Free, HEAP_SIZE, HeapHead, MemBlock, MemBlockLocation, ROM_START,
+};
+use crate::{info, resources, warn};
+
+const DEFAULT_TAG: &CStr = c"RUST";
+
+/// Allocation counters, only touched from the main loop
+struct Counters {
+    live: Cell<usize>,
+    peak: Cell<usize>,
+    allocations: Cell<usize>,
+    tag: Cell<&'static CStr>,
+}
+unsafe impl Sync for Counters {}
+
+static COUNTERS: Counters = Counters {
+    live: Cell::new(0),
+    peak: Cell::new(0),
+    allocations: Cell::new(0),
+    tag: Cell::new(DEFAULT_TAG),
+};
+
+struct PokeAllocator;
+unsafe impl GlobalAlloc for PokeAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let tag = COUNTERS.tag.get();
+        // `Alloc_` flushes the resource cache and retries before giving up
+        let ptr = unsafe { Alloc_(layout.size() as u32, tag.as_ptr()) } as *mut u8;
+        // Infallible allocations panic in `handle_alloc_error`, the others return an error
+        if ptr.is_null() {
+            return ptr;
+        }
+
+        let live = COUNTERS.live.get() + layout.size();
+        COUNTERS.live.set(live);
+        COUNTERS.peak.set(COUNTERS.peak.get().max(live));
+        COUNTERS.allocations.set(COUNTERS.allocations.get() + 1);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { Free(ptr as *mut _) }
+        COUNTERS.live.set(COUNTERS.live.get() - layout.size());
+        COUNTERS.allocations.set(COUNTERS.allocations.get() - 1);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PokeAllocator = PokeAllocator;
+
+/// A scene ran out of one of the shared pools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfMemory {
+    /// `gHeap` couldn't fit an allocation of this many bytes
+    Heap(usize),
+    Sprites,
+    SpriteTiles,
+    Windows,
+}
+
+/// Zeroed heap buffer, without panicking when `gHeap` is full
+pub fn try_alloc_bytes(size: usize) -> Result<Box<[u8]>, OutOfMemory> {
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(size)
+        .map_err(|_| OutOfMemory::Heap(size))?;
+    buffer.resize(size, 0);
+    Ok(buffer.into_boxed_slice())
+}
+
+/// `Box::new`, without panicking when `gHeap` is full
+pub fn try_box<T>(value: T) -> Result<Box<T>, OutOfMemory> {
+    let layout = Layout::new::<T>();
+    if layout.size() == 0 {
+        return Ok(Box::new(value));
+    }
+    let ptr = unsafe { alloc::alloc::alloc(layout) }.cast::<T>();
+    if ptr.is_null() {
+        return Err(OutOfMemory::Heap(layout.size()));
+    }
+    unsafe {
+        ptr.write(value);
+        Ok(Box::from_raw(ptr))
+    }
+}
+
+/// Sets the location recorded by `Alloc_` for Rust allocations, returning the previous one
+///
+/// The tag must be a literal, as `gHeap` blocks only keep the low bits of a rom address.
+pub fn set_tag(tag: &'static CStr) -> &'static CStr {
+    COUNTERS.tag.replace(tag)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeapStats {
+    /// Bytes currently allocated from Rust
+    pub live: usize,
+    /// Highest `live` since boot
+    pub peak: usize,
+    /// Rust allocations not freed yet
+    pub allocations: usize,
+    /// Bytes of `gHeap` used by both C and Rust, headers included
+    pub heap_used: usize,
+    pub largest_free: usize,
+}
+
+pub fn heap_stats() -> HeapStats {
+    let mut heap_used = 0;
+    let mut largest_free = 0;
+    for block in blocks() {
+        let size = block.size() as usize;
+        match block.allocated() != 0 {
+            true => heap_used += size + size_of::<MemBlock>(),
+            false => largest_free = largest_free.max(size),
+        }
+    }
+    HeapStats {
+        live: COUNTERS.live.get(),
+        peak: COUNTERS.peak.get(),
+        allocations: COUNTERS.allocations.get(),
+        heap_used,
+        largest_free,
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TagStats {
+    location: *const c_char,
+    pub blocks: usize,
+    pub bytes: usize,
+}
+
+impl TagStats {
+    /// `None` for C allocations in builds without locations
+    ///
+    /// `MemBlockLocation` turns their `NULL` into the start of the rom, as it
+    /// only stores the low bits of the address.
+    pub fn tag(&self) -> Option<&'static CStr> {
+        match self.location.is_null() || self.location.addr() == ROM_START as usize {
+            true => None,
+            false => Some(unsafe { CStr::from_ptr(self.location) }),
+        }
+    }
+}
+
+impl fmt::Debug for TagStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = self.tag().and_then(|tag| tag.to_str().ok());
+        write!(
+            f,
+            "{}: {} blocks, {} bytes",
+            tag.unwrap_or("?"),
+            self.blocks,
+            self.bytes
+        )
+    }
+}
+
+/// Allocated blocks of `gHeap` grouped by location, locations past the 16th counted in the last one
+pub fn tag_stats() -> ArrayVec<TagStats, 16> {
+    let mut tags: ArrayVec<TagStats, 16> = ArrayVec::new();
+    for block in blocks().filter(|block| block.allocated() != 0) {
+        let location = unsafe { MemBlockLocation(block) };
+        let index = match tags.iter().position(|tag| tag.location == location) {
+            Some(index) => index,
+            None if !tags.is_full() => {
+                tags.push(TagStats {
+                    location,
+                    blocks: 0,
+                    bytes: 0,
+                });
+                tags.len() - 1
+            }
+            None => tags.len() - 1,
+        };
+        tags[index].blocks += 1;
+        tags[index].bytes += block.size() as usize;
+    }
+    tags
+}
+
+/// Blocks of `gHeap` in order
+///
+/// The panic handler walks them too, so a corrupted list stops at the first
+/// block outside of `gHeap` rather than looping or faulting.
+fn blocks() -> impl Iterator<Item = &'static MemBlock> {
+    let head = unsafe { HeapHead() };
+    let headers = head.addr()..=head.addr() + HEAP_SIZE as usize - size_of::<MemBlock>();
+    let mut block = head;
+    core::iter::from_fn(move || {
+        if block.is_null() || !headers.contains(&block.addr()) {
+            return None;
+        }
+        let current = unsafe { &*block };
+        block = match current.next.cast_const() == head {
+            true => core::ptr::null(),
+            false => current.next.cast_const(),
+        };
+        Some(current)
+    })
+    .take(HEAP_SIZE as usize / size_of::<MemBlock>())
+}
+
+pub fn log_heap_stats() {
+    let stats = heap_stats();
+    info!("{stats:?} of {HEAP_SIZE} bytes");
+    for tag in tag_stats() {
+        info!("  {tag:?}");
+    }
+}
+
+/// Heap usage at the start of a scene, compared with the usage on exit
+pub struct HeapScope {
+    live: usize,
+    allocations: usize,
+    cached: usize,
+    previous_tag: &'static CStr,
+}
+
+impl HeapScope {
+    pub fn enter(tag: &'static CStr) -> Self {
+        HeapScope {
+            live: COUNTERS.live.get(),
+            allocations: COUNTERS.allocations.get(),
+            cached: resources::cached_bytes(),
+            previous_tag: set_tag(tag),
+        }
+    }
+
+    /// Logs what the scene left allocated, not counting resources kept in the cache
+    pub fn exit(self) {
+        let tag = set_tag(self.previous_tag);
+        let cached = resources::cached_bytes().saturating_sub(self.cached);
+        let leaked = COUNTERS.live.get().saturating_sub(self.live + cached);
+        if leaked == 0 {
+            return;
+        }
+
+        let allocations = COUNTERS.allocations.get() as isize - self.allocations as isize;
+        warn!("{tag:?} leaked {leaked} bytes, {allocations} allocations");
+        let blocks = tag_stats()
+            .into_iter()
+            .filter(|stats| stats.tag() == Some(tag));
+        for stats in blocks {
+            warn!("  {stats:?}");
+        }
+    }
+}
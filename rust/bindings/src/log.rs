@@ -0,0 +1,338 @@
+//! Leveled logging to a set of backends
+//!
+//! The most verbose level compiled in is picked with the `log-*` features,
+//! anything above it is removed at compile time along with its arguments.
+//! Messages longer than the mGBA buffer are split over several lines, and
+//! each line goes to every backend: the mGBA and no$gba debug ports, which
+//! do nothing on other emulators or hardware, and a ring buffer in EWRAM
+//! that the crash screen shows. With the `sram-dump` feature the crash screen
+//! also copies it to SRAM, where it outlives the reset.
+
+use core::cell::Cell;
+use core::fmt::{self, Arguments, Write};
+
+use arrayvec::ArrayVec;
+
+use crate::future::RefCellSync;
+pub use crate::log_ring::{LINE_SIZE, Level, RingBuffer};
+
+impl Level {
+    /// mGBA has no trace level, those are shown as debug
+    fn mgba(self) -> u16 {
+        (self as u16).min(Level::Debug as u16)
+    }
+}
+
+/// Most verbose level compiled in, `None` when logging is off
+pub const MAX_LEVEL: Option<Level> = if cfg!(feature = "log-trace") {
+    Some(Level::Trace)
+} else if cfg!(feature = "log-debug") {
+    Some(Level::Debug)
+} else if cfg!(feature = "log-info") {
+    Some(Level::Info)
+} else if cfg!(feature = "log-warn") {
+    Some(Level::Warn)
+} else if cfg!(feature = "log-error") {
+    Some(Level::Error)
+} else {
+    None
+};
+
+pub const fn enabled(level: Level) -> bool {
+    match MAX_LEVEL {
+        Some(max) => level as u8 <= max as u8,
+        None => false,
+    }
+}
+
+/// Destination for log lines
+pub trait Backend: Sync {
+    /// Writes a line of at most [`LINE_SIZE`] bytes, without a line break
+    fn write(&self, level: Level, line: &str);
+}
+
+/// mGBA debug registers
+pub struct Mgba;
+
+impl Mgba {
+    const STRING: *mut u8 = 0x4FFF600 as _;
+    const FLAGS: *mut u16 = 0x4FFF700 as _;
+    const ENABLE: *mut u16 = 0x4FFF780 as _;
+
+    /// Enables the debug registers on the first line, later ones reuse the answer
+    fn detect() -> bool {
+        if let Some(detected) = MGBA_DETECTED.0.get() {
+            return detected;
+        }
+        let detected = unsafe {
+            Self::ENABLE.write_volatile(0xC0DE);
+            Self::ENABLE.read_volatile() == 0x1DEA
+        };
+        MGBA_DETECTED.0.set(Some(detected));
+        detected
+    }
+}
+
+struct Detected(Cell<Option<bool>>);
+unsafe impl Sync for Detected {}
+
+static MGBA_DETECTED: Detected = Detected(Cell::new(None));
+
+impl Backend for Mgba {
+    fn write(&self, level: Level, line: &str) {
+        if !Self::detect() {
+            return;
+        }
+        unsafe {
+            for (i, &byte) in line.as_bytes().iter().enumerate() {
+                Self::STRING.add(i).write_volatile(byte);
+            }
+            Self::STRING.add(line.len()).write_volatile(0);
+            Self::FLAGS.write_volatile(level.mgba() | 0x100);
+        }
+    }
+}
+
+/// no$gba debug message registers
+pub struct NoCash;
+
+impl NoCash {
+    const ID: *const [u8; 6] = 0x4FFFA00 as _;
+    /// Takes the address of a string, printed without parsing `%` parameters
+    const STRING_OUT: *mut u32 = 0x4FFFA10 as _;
+    const CHAR_OUT: *mut u32 = 0x4FFFA1C as _;
+
+    fn detect() -> bool {
+        unsafe { Self::ID.read_volatile() == *b"no$gba" }
+    }
+}
+
+impl Backend for NoCash {
+    fn write(&self, level: Level, line: &str) {
+        if !Self::detect() {
+            return;
+        }
+        let mut text: ArrayVec<u8, { LINE_SIZE + 4 }> = ArrayVec::new();
+        text.extend([level.letter() as u8, b' ']);
+        _ = text.try_extend_from_slice(line.as_bytes());
+        text.push(0);
+        unsafe {
+            Self::STRING_OUT.write_volatile(text.as_ptr() as u32);
+            Self::CHAR_OUT.write_volatile(b'\n' as u32);
+        }
+    }
+}
+
+/// Ring buffer shared as a backend
+pub struct Ring<const SIZE: usize>(RefCellSync<RingBuffer<SIZE>>);
+
+impl<const SIZE: usize> Ring<SIZE> {
+    pub const fn new() -> Self {
+        Ring(RefCellSync::new(RingBuffer::new()))
+    }
+
+    /// Calls `f` on the last `count` lines, oldest first
+    pub fn tail(&self, count: usize, mut f: impl FnMut(Level, &str)) {
+        let Ok(ring) = self.0.try_borrow() else {
+            return;
+        };
+        let skip = ring.lines().saturating_sub(count);
+        let mut index = 0;
+        ring.for_each(|level, line| {
+            if index >= skip {
+                f(level, line);
+            }
+            index += 1;
+        });
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    /// Copies the lines to SRAM as text, ending with a 0, to read them from
+    /// the save file of a run that crashed
+    ///
+    /// Stops at [`SRAM_DUMP_SIZE`], below the addresses of the flash
+    /// commands, so it doesn't touch the save on Emerald's flash carts.
+    ///
+    /// # Safety
+    /// Nothing else may be using the save memory.
+    pub unsafe fn dump_to_sram(&self) {
+        const SRAM: *mut u8 = 0xE000000 as _;
+        let mut offset = 0;
+        let mut write = |byte: u8| {
+            if offset < SRAM_DUMP_SIZE - 1 {
+                unsafe { SRAM.add(offset).write_volatile(byte) };
+                offset += 1;
+            }
+        };
+        self.tail(usize::MAX, |level, line| {
+            write(level.letter() as u8);
+            write(b' ');
+            line.bytes().for_each(&mut write);
+            write(b'\n');
+        });
+        unsafe { SRAM.add(offset).write_volatile(0) };
+    }
+}
+
+/// Most bytes written by [`Ring::dump_to_sram`]
+pub const SRAM_DUMP_SIZE: usize = 0x2000;
+
+impl<const SIZE: usize> Default for Ring<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SIZE: usize> Backend for Ring<SIZE> {
+    fn write(&self, level: Level, line: &str) {
+        if let Ok(mut ring) = self.0.try_borrow_mut() {
+            ring.push(level, line);
+        }
+    }
+}
+
+crate::ewram! {
+    /// Recent lines, shown on the crash screen
+    pub static RING: Ring<0x1000> = Ring::new();
+}
+
+struct Backends(Cell<&'static [&'static dyn Backend]>);
+unsafe impl Sync for Backends {}
+
+static BACKENDS: Backends = Backends(Cell::new(&[&Mgba, &NoCash, &RING]));
+
+/// Replaces the backends lines are written to
+pub fn set_backends(backends: &'static [&'static dyn Backend]) {
+    BACKENDS.0.set(backends);
+}
+
+/// Message being written, sent to the backends every time a line fills up
+struct Chunks {
+    level: Level,
+    buf: ArrayVec<u8, LINE_SIZE>,
+}
+
+impl Chunks {
+    const fn new(level: Level) -> Self {
+        Chunks {
+            level,
+            buf: ArrayVec::new_const(),
+        }
+    }
+
+    fn flush(&mut self) {
+        // Only whole chars are written to the buffer
+        let line = unsafe { core::str::from_utf8_unchecked(&self.buf) };
+        for backend in BACKENDS.0.get() {
+            backend.write(self.level, line);
+        }
+        self.buf.clear();
+    }
+}
+
+impl Write for Chunks {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for char in s.chars() {
+            let mut bytes = [0; 4];
+            let bytes = char.encode_utf8(&mut bytes).as_bytes();
+            if self.buf.remaining_capacity() < bytes.len() {
+                self.flush();
+            }
+            _ = self.buf.try_extend_from_slice(bytes);
+        }
+        Ok(())
+    }
+}
+
+crate::ewram! {
+    static CHUNKS: RefCellSync<Chunks> = RefCellSync::new(Chunks::new(Level::Error));
+}
+
+/// Writes a message, dropped if it's logged while formatting another one
+pub fn print(level: Level, args: Arguments) {
+    let Ok(mut chunks) = CHUNKS.try_borrow_mut() else {
+        return;
+    };
+    chunks.level = level;
+    _ = chunks.write_fmt(args);
+    chunks.flush();
+}
+
+/// Writes a message from the stack, for the panic handler which may have
+/// interrupted another message
+pub(crate) fn print_unbuffered(level: Level, args: Arguments) {
+    let mut chunks = Chunks::new(level);
+    _ = chunks.write_fmt(args);
+    chunks.flush();
+}
+
+#[doc(hidden)]
+pub fn log(level: Level, target: &str, args: Arguments) {
+    print(level, format_args!("[{target}] {args}"));
+}
+
+/// Logs at `level` with the module path as target, or with `target: "..."` first
+#[macro_export]
+macro_rules! log {
+    (target: $target:expr, $level:expr, $($arg:tt)+) => {
+        if $crate::log::enabled($level) {
+            $crate::log::log($level, $target, format_args!($($arg)+))
+        }
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log!(target: module_path!(), $level, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! error {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Error, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Warn, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Info, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Debug, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::log!(target: $target, $crate::log::Level::Trace, $($arg)+)
+    };
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)+)
+    };
+}
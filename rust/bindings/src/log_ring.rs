@@ -0,0 +1,115 @@
+//! Log levels and the ring buffer of recent lines, apart from the rest of
+//! the `log` module as they only depend on `core` and arrayvec, so they're also
+//! built for the host where `host-tests` checks them.
+
+use arrayvec::ArrayVec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// Level from an mGBA one, where fatal is shown as an error
+    pub const fn from_mgba(level: i32) -> Level {
+        match level {
+            ..=1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+
+    fn from_u8(level: u8) -> Level {
+        Level::from_mgba(level as i32)
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Level::Error => 'E',
+            Level::Warn => 'W',
+            Level::Info => 'I',
+            Level::Debug => 'D',
+            Level::Trace => 'T',
+        }
+    }
+}
+
+/// Longest line sent to the backends, one less than the mGBA debug string for the terminator
+pub const LINE_SIZE: usize = 0xFF;
+
+/// Most recent lines, oldest dropped first, stored as level, length and bytes
+pub struct RingBuffer<const SIZE: usize> {
+    data: [u8; SIZE],
+    /// Index of the oldest record
+    start: usize,
+    len: usize,
+}
+
+impl<const SIZE: usize> RingBuffer<SIZE> {
+    pub const fn new() -> Self {
+        RingBuffer {
+            data: [0; SIZE],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    fn byte(&self, offset: usize) -> u8 {
+        self.data[(self.start + offset) % SIZE]
+    }
+
+    fn drop_oldest(&mut self) {
+        let size = 2 + self.byte(1) as usize;
+        self.start = (self.start + size) % SIZE;
+        self.len -= size;
+    }
+
+    pub fn push(&mut self, level: Level, line: &str) {
+        let line = &line.as_bytes()[..line.len().min(LINE_SIZE).min(SIZE - 2)];
+        while SIZE - self.len < line.len() + 2 {
+            self.drop_oldest();
+        }
+        let record = [level as u8, line.len() as u8].into_iter();
+        for (i, byte) in record.chain(line.iter().copied()).enumerate() {
+            self.data[(self.start + self.len + i) % SIZE] = byte;
+        }
+        self.len += line.len() + 2;
+    }
+
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+
+    /// Calls `f` on each line, oldest first
+    pub fn for_each(&self, mut f: impl FnMut(Level, &str)) {
+        let mut offset = 0;
+        while offset < self.len {
+            let level = Level::from_u8(self.byte(offset));
+            let len = self.byte(offset + 1) as usize;
+            let mut line: ArrayVec<u8, LINE_SIZE> = ArrayVec::new();
+            line.extend((0..len).map(|i| self.byte(offset + 2 + i)));
+            // Lines are only cut at char boundaries, but a truncated one may not be
+            f(level, core::str::from_utf8(&line).unwrap_or("?"));
+            offset += 2 + len;
+        }
+    }
+
+    pub fn lines(&self) -> usize {
+        let mut count = 0;
+        self.for_each(|_, _| count += 1);
+        count
+    }
+}
+
+impl<const SIZE: usize> Default for RingBuffer<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
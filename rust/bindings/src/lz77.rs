@@ -0,0 +1,121 @@
+//! LZ77 decoder for the BIOS format (type 0x10), as an alternative to the
+//! uninterruptible `LZ77UnCompWram` call.
+//!
+//! This module only depends on `core`, so it's also built for the host where
+//! `host-tests` checks it against files compressed by gbagfx.
+
+/// Decodes LZ77 data a bounded number of bytes at a time
+pub struct Lz77Decoder {
+    src: *const u8,
+    len: usize,
+    written: usize,
+    flags: u8,
+    flag_bits: u8,
+}
+
+impl Lz77Decoder {
+    /// # Safety
+    /// `src` must point to valid LZ77 data, and outlive the decoder
+    pub unsafe fn new(src: *const u8) -> Self {
+        let header = unsafe { src.cast::<[u8; 4]>().read_unaligned() };
+        if header[0] != 0x10 {
+            panic!("Not LZ77 data: {:#x}", header[0]);
+        }
+        Lz77Decoder {
+            src: unsafe { src.add(4) },
+            len: u32::from_le_bytes(header) as usize >> 8,
+            written: 0,
+            flags: 0,
+            flag_bits: 0,
+        }
+    }
+
+    /// Size of the decompressed data
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.written >= self.len
+    }
+
+    crate::iwram! {
+        /// Decodes about `budget` more bytes into `dest`, returning whether it's done
+        ///
+        /// A match is never split, so this may write up to 17 bytes over budget.
+        /// `dest` must be the same buffer on every call.
+        ///
+        /// Runs from IWRAM as ARM code to keep up with the BIOS decoder, compare
+        /// them with `benchmark_lz77`. It doesn't call out of IWRAM except to panic.
+        pub fn decode(&mut self, dest: &mut [u8], budget: usize) -> bool {
+            if dest.len() < self.len {
+                panic!("LZ77 destination too small: {} < {}", dest.len(), self.len);
+            }
+
+            let end = self.len.min(self.written.saturating_add(budget));
+            let out = dest.as_mut_ptr();
+            let mut src = self.src;
+            let mut written = self.written;
+            let mut flags = self.flags;
+            let mut flag_bits = self.flag_bits;
+
+            // Safety: the header size was checked against `dest`, and matches are
+            // checked to only reference bytes already written
+            unsafe {
+                while written < end {
+                    if flag_bits == 0 {
+                        flags = *src;
+                        src = src.add(1);
+                        flag_bits = 8;
+                    }
+                    flag_bits -= 1;
+
+                    match flags & 0x80 != 0 {
+                        true => {
+                            let (high, low) = (*src as usize, *src.add(1) as usize);
+                            src = src.add(2);
+                            let len = ((high >> 4) + 3).min(self.len - written);
+                            let distance = ((high & 0xF) << 8 | low) + 1;
+                            if distance > written {
+                                panic!("LZ77 match {distance} bytes back, after {written} bytes");
+                            }
+                            let mut from = out.add(written - distance);
+                            let to_end = out.add(written + len);
+                            let mut to = out.add(written);
+                            while to < to_end {
+                                *to = *from;
+                                to = to.add(1);
+                                from = from.add(1);
+                            }
+                            written += len;
+                        }
+                        false => {
+                            *out.add(written) = *src;
+                            src = src.add(1);
+                            written += 1;
+                        }
+                    }
+                    flags <<= 1;
+                }
+            }
+
+            self.src = src;
+            self.written = written;
+            self.flags = flags;
+            self.flag_bits = flag_bits;
+            self.is_done()
+        }
+    }
+}
+
+/// Decompresses the whole of `src` into `dest`, which must fit it
+///
+/// # Safety
+/// `src` must point to valid LZ77 data
+pub unsafe fn decompress(src: *const u8, dest: &mut [u8]) {
+    unsafe { Lz77Decoder::new(src) }.decode(dest, usize::MAX);
+}
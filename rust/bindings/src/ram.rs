@@ -0,0 +1,106 @@
+//! Placement of hot code and data in the GBA's faster memories
+//!
+//! IWRAM is 32 bit with no wait states, so ARM code there runs several times
+//! faster than thumb code from rom. It's only 32 KB, shared with the C
+//! statics and the stack, so keep it for hot leaf code like the LZ77 decoder
+//! or the tile gather loop of `WindowHandle::copy_tilemap`,
+//! with timer measurements showing the gain (see `benchmark_lz77` in
+//! [`crate::resources`]), and check [`iwram_usage`] when adding more.
+//! EWRAM is 256 KB of slower memory for large statics that don't need to be
+//! in `.bss`.
+
+use core::fmt;
+
+use crate::info;
+
+/// Puts a function in IWRAM as ARM code, or a static in IWRAM
+///
+/// The linker script copies `.iwram*` sections from rom at boot, and
+/// collects the Rust ones between `__rust_iwram_start` and `__rust_iwram_end`.
+/// Functions calling back into rom, or the C side, spend most of their time
+/// there and gain little from it.
+#[macro_export]
+macro_rules! iwram {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        $(#[$attr])*
+        #[cfg_attr(target_arch = "arm", unsafe(link_section = ".iwram.text"))]
+        #[cfg_attr(target_arch = "arm", instruction_set(arm::a32))]
+        #[inline(never)]
+        $vis fn $($rest)*
+    };
+    ($(#[$attr:meta])* $vis:vis static $($rest:tt)*) => {
+        $(#[$attr])*
+        #[unsafe(link_section = ".iwram.data")]
+        $vis static $($rest)*
+    };
+}
+
+/// Puts a static in EWRAM rather than in IWRAM with the rest of the Rust `.bss`
+#[macro_export]
+macro_rules! ewram {
+    ($(#[$attr:meta])* $vis:vis static $($rest:tt)*) => {
+        $(#[$attr])*
+        #[unsafe(link_section = ".ewram.data")]
+        $vis static $($rest)*
+    };
+}
+
+/// Budget for Rust code and data in IWRAM, also checked by the linker scripts
+pub const RUST_IWRAM_BUDGET: usize = 0x1000;
+
+const IWRAM_START: usize = 0x3000000;
+/// Bottom of the IRQ and system stacks set up by `crt0.s`
+const STACK_START: usize = IWRAM_START + 0x8000 - 0x1C0;
+
+#[derive(Clone, Copy)]
+pub struct IwramUsage {
+    /// Bytes of `.iwram*` sections placed from Rust
+    pub rust: usize,
+    /// Bytes of `.iwram*` sections from both C and Rust
+    pub code: usize,
+    /// Bytes of code and statics, up to the end of `.bss`
+    pub statics: usize,
+    /// Bytes left for the stack to grow into
+    pub stack: usize,
+}
+
+/// End of the IWRAM statics, the lowest address the stack can grow to
+pub(crate) fn statics_end() -> usize {
+    unsafe extern "C" {
+        static __iwram_bss_end: u8;
+    }
+    (&raw const __iwram_bss_end) as usize
+}
+
+pub fn iwram_usage() -> IwramUsage {
+    unsafe extern "C" {
+        static __iwram_start: u8;
+        static __iwram_end: u8;
+        static __rust_iwram_start: u8;
+        static __rust_iwram_end: u8;
+    }
+    let addr = |symbol: *const u8| symbol as usize;
+    let statics_end = statics_end();
+    unsafe {
+        IwramUsage {
+            rust: addr(&raw const __rust_iwram_end) - addr(&raw const __rust_iwram_start),
+            code: addr(&raw const __iwram_end) - addr(&raw const __iwram_start),
+            statics: statics_end - IWRAM_START,
+            stack: STACK_START.saturating_sub(statics_end),
+        }
+    }
+}
+
+impl fmt::Debug for IwramUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "IWRAM: Rust {}/{RUST_IWRAM_BUDGET}, sections {}, statics {}, stack {} bytes",
+            self.rust, self.code, self.statics, self.stack
+        )
+    }
+}
+
+pub fn log_iwram_usage() {
+    info!("{:?}", iwram_usage());
+}
@@ -1,10 +1,15 @@
 use alloc::boxed::Box;
-use alloc::vec;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
 use core::cell::{Ref, RefCell, RefMut};
 use core::marker::PhantomData;
 use core::ops::Deref;
 
-use crate::pokeemerald::LZ77UnCompWram;
+use crate::future::{RefCellSync, sleep};
+use crate::graphics::{Rect, ScreenSize, Vec2D};
+use crate::heap::{OutOfMemory, try_alloc_bytes};
+use crate::lz77::Lz77Decoder;
+use crate::pokeemerald::{HuffUnComp, LZ77UnCompVram, LZ77UnCompWram, RLUnCompVram, RLUnCompWram};
 
 pub struct AllocBuf<T: Sized> {
     data: RefCell<Box<[u8]>>,
@@ -26,6 +31,10 @@ impl<T> AllocBuf<T> {
     pub fn as_mut_ptr(&self) -> *mut T {
         self.data.borrow_mut().as_mut_ptr().cast()
     }
+
+    pub fn into_inner(self) -> Box<[u8]> {
+        self.data.into_inner()
+    }
 }
 
 pub trait Buffer<T> {
@@ -55,42 +64,428 @@ impl<T> Buffer<T> for &[T] {
     }
 }
 
-unsafe impl<const C: usize> Sync for CompressedResource<C> {}
-pub struct CompressedResource<const SIZE: usize> {
-    data: *const u8,
+/// BIOS compression formats, from the type in the data's header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Lz77 = 0x10,
+    Huffman = 0x20,
+    RunLength = 0x30,
 }
 
-pub const fn lz_ptr_res<const SIZE: usize>(data: *const u8) -> CompressedResource<SIZE> {
-    CompressedResource { data }
+impl Compression {
+    fn of(data: *const u8) -> Self {
+        match unsafe { *data } & 0xF0 {
+            0x10 => Compression::Lz77,
+            0x20 => Compression::Huffman,
+            0x30 => Compression::RunLength,
+            header => panic!("Unknown compression type {header:#x}"),
+        }
+    }
 }
 
-impl<const SIZE: usize> CompressedResource<SIZE> {
-    pub const fn from_ref(data: &'static [u8]) -> Self {
-        CompressedResource {
-            data: data.as_ptr(),
+/// Decompresses with the matching BIOS call, `vram` selecting the
+/// variants writing 16 bits at a time
+unsafe fn decompress_to(data: *const u8, dest: *mut u8, vram: bool) {
+    unsafe {
+        match (Compression::of(data), vram) {
+            (Compression::Lz77, false) => LZ77UnCompWram(data.cast(), dest.cast()),
+            (Compression::Lz77, true) => LZ77UnCompVram(data.cast(), dest.cast()),
+            (Compression::RunLength, false) => RLUnCompWram(data.cast(), dest.cast()),
+            (Compression::RunLength, true) => RLUnCompVram(data.cast(), dest.cast()),
+            (Compression::Huffman, _) => HuffUnComp(data.cast(), dest.cast()),
         }
     }
+}
+
+fn try_decompress<T>(data: *const u8, size: usize) -> Result<AllocBuf<T>, OutOfMemory> {
+    let mut load = try_alloc_bytes(size)?;
+    unsafe { decompress_to(data, load.as_mut_ptr(), false) };
+    Ok(AllocBuf::new(load))
+}
+
+fn decompress<T>(data: *const u8, size: usize) -> AllocBuf<T> {
+    try_decompress(data, size).unwrap_or_else(|err| panic!("Couldn't load resource: {err:?}"))
+}
 
-    pub fn load<T: Sized>(&self) -> AllocBuf<T> {
+/// LZ77 compressed data decompressing to `SIZE` bytes of `T`
+unsafe impl<T, const C: usize> Sync for CompressedResource<T, C> {}
+pub struct CompressedResource<T, const SIZE: usize> {
+    data: *const u8,
+    _p: PhantomData<T>,
+}
+
+pub const fn lz_ptr_res<T, const SIZE: usize>(data: *const u8) -> CompressedResource<T, SIZE> {
+    CompressedResource {
+        data,
+        _p: PhantomData,
+    }
+}
+
+impl<T, const SIZE: usize> CompressedResource<T, SIZE> {
+    pub const fn from_ref(data: &'static [u8]) -> Self {
         const {
             if SIZE % size_of::<T>() != 0 {
                 panic!("Invalid length")
             };
         }
+        lz_ptr_res(data.as_ptr())
+    }
+
+    pub const fn len(&self) -> usize {
+        SIZE / size_of::<T>()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        SIZE == 0
+    }
+
+    pub fn compression(&self) -> Compression {
+        Compression::of(self.data)
+    }
+
+    pub fn load(&self) -> AllocBuf<T> {
+        decompress(self.data, SIZE)
+    }
+
+    pub fn try_load(&self) -> Result<AllocBuf<T>, OutOfMemory> {
+        try_decompress(self.data, SIZE)
+    }
+
+    /// Decompresses about `bytes_per_frame` bytes each frame instead of
+    /// blocking in a BIOS call, other formats than LZ77 are loaded at once
+    pub async fn load_incremental(&self, bytes_per_frame: usize) -> AllocBuf<T> {
+        self.try_load_incremental(bytes_per_frame)
+            .await
+            .unwrap_or_else(|err| panic!("Couldn't load resource: {err:?}"))
+    }
+
+    pub async fn try_load_incremental(
+        &self,
+        bytes_per_frame: usize,
+    ) -> Result<AllocBuf<T>, OutOfMemory> {
+        if self.compression() != Compression::Lz77 {
+            return self.try_load();
+        }
+        let mut load = try_alloc_bytes(SIZE)?;
+        let mut decoder = unsafe { Lz77Decoder::new(self.data) };
+        while !decoder.decode(&mut load, bytes_per_frame) {
+            sleep(1).await;
+        }
+        Ok(AllocBuf::new(load))
+    }
+
+    /// Decompresses once and shares the buffer until it's evicted, see [`evict_unused`]
+    pub fn load_cached(&self) -> Cached<T> {
+        self.try_load_cached()
+            .unwrap_or_else(|err| panic!("Couldn't load resource: {err:?}"))
+    }
+
+    pub fn try_load_cached(&self) -> Result<Cached<T>, OutOfMemory> {
+        match ResourceCache::lookup(self.data) {
+            Some(cached) => Ok(cached),
+            None => ResourceCache::insert(self.data, self.try_load()?),
+        }
+    }
+
+    /// [`Self::load_cached`], decompressing with [`Self::load_incremental`] on a miss
+    pub async fn load_cached_incremental(&self, bytes_per_frame: usize) -> Cached<T> {
+        self.try_load_cached_incremental(bytes_per_frame)
+            .await
+            .unwrap_or_else(|err| panic!("Couldn't load resource: {err:?}"))
+    }
+
+    pub async fn try_load_cached_incremental(
+        &self,
+        bytes_per_frame: usize,
+    ) -> Result<Cached<T>, OutOfMemory> {
+        match ResourceCache::lookup(self.data) {
+            Some(cached) => Ok(cached),
+            None => {
+                let load = self.try_load_incremental(bytes_per_frame).await?;
+                ResourceCache::insert(self.data, load)
+            }
+        }
+    }
+
+    pub(crate) unsafe fn decompress_into(&self, dest: *mut u8) {
+        unsafe { decompress_to(self.data, dest, false) }
+    }
+
+    /// Decompresses straight into vram, without a heap copy
+    ///
+    /// # Safety
+    /// `dest` must be a vram address with room for `SIZE` bytes
+    pub unsafe fn load_to_vram(&self, dest: *mut T) {
+        unsafe { decompress_to(self.data, dest.cast(), true) }
+    }
+}
+
+/// Logs the cycles taken by the BIOS and the Rust LZ77 decoders on `resource`
+///
+/// Uses timers 2 and 3, which must not be running, and decompresses twice
+/// at once, so it's only called by hand when working on the decoder.
+#[cfg(feature = "debug")]
+pub fn benchmark_lz77<T, const SIZE: usize>(resource: &CompressedResource<T, SIZE>) {
+    if resource.compression() != Compression::Lz77 {
+        return;
+    }
+    use crate::pokeemerald::{REG_ADDR_TM2CNT, TIMER_1CLK, TIMER_COUNTUP, TIMER_ENABLE};
+
+    let timers = REG_ADDR_TM2CNT as *mut [u32; 2];
+    let time = |f: &mut dyn FnMut()| unsafe {
+        let control = (TIMER_ENABLE | TIMER_1CLK) << 16;
+        let cascade = (TIMER_ENABLE | TIMER_COUNTUP) << 16;
+        timers.write_volatile([0, 0]);
+        timers.write_volatile([control, cascade]);
+        f();
+        let [low, high] = timers.read_volatile();
+        timers.write_volatile([0, 0]);
+        (low & 0xFFFF) | (high & 0xFFFF) << 16
+    };
+
+    let mut dest = alloc::vec![0u8; SIZE];
+    let bios =
+        time(&mut || unsafe { LZ77UnCompWram(resource.data.cast(), dest.as_mut_ptr().cast()) });
+    let rust = time(&mut || unsafe { crate::lz77::decompress(resource.data, &mut dest) });
+    crate::info!(
+        "LZ77 {} bytes: bios {} cycles, rust {} cycles",
+        SIZE,
+        bios,
+        rust
+    );
+}
+
+impl<const SIZE: usize> CompressedResource<u16, SIZE> {
+    /// Number of 16 color palettes, counting a trailing partial one
+    pub const PALETTES: usize = SIZE.div_ceil(32);
+}
+
+/// Decompressed resource shared through the resource cache
+pub struct Cached<T> {
+    data: Rc<Box<[u8]>>,
+    _p: PhantomData<T>,
+}
+
+impl<T> Clone for Cached<T> {
+    fn clone(&self) -> Self {
+        Cached {
+            data: self.data.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T> Buffer<T> for Cached<T> {
+    fn get(&self) -> impl Deref<Target = [T]> {
+        unsafe { self.data.align_to().1 }
+    }
+}
+impl<T> Buffer<T> for &Cached<T> {
+    fn get(&self) -> impl Deref<Target = [T]> {
+        (*self).get()
+    }
+}
+
+struct CacheEntry {
+    key: *const u8,
+    data: Rc<Box<[u8]>>,
+    last_use: u32,
+}
+
+impl CacheEntry {
+    fn unused(&self) -> bool {
+        Rc::strong_count(&self.data) == 1
+    }
+}
+
+/// Unreferenced resources kept around for the next screen, up to this many bytes
+const CACHE_BUDGET: usize = 0x4000;
+
+/// Decompressed resources keyed by the address of their compressed data
+struct ResourceCache {
+    entries: Vec<CacheEntry>,
+    clock: u32,
+}
+
+static RESOURCE_CACHE: RefCellSync<ResourceCache> = RefCellSync::new(ResourceCache {
+    entries: Vec::new(),
+    clock: 0,
+});
+
+impl ResourceCache {
+    fn lookup<T>(key: *const u8) -> Option<Cached<T>> {
+        let mut cache = RESOURCE_CACHE.borrow_mut();
+        cache.clock += 1;
+        let clock = cache.clock;
+        let entry = cache.entries.iter_mut().find(|entry| entry.key == key)?;
+        entry.last_use = clock;
+        Some(Cached {
+            data: entry.data.clone(),
+            _p: PhantomData,
+        })
+    }
+
+    fn insert<T>(key: *const u8, load: AllocBuf<T>) -> Result<Cached<T>, OutOfMemory> {
+        let data = Rc::new(load.into_inner());
+        let mut cache = RESOURCE_CACHE.borrow_mut();
+        cache
+            .entries
+            .try_reserve(1)
+            .map_err(|_| OutOfMemory::Heap(size_of::<CacheEntry>()))?;
+        let last_use = cache.clock;
+        cache.entries.push(CacheEntry {
+            key,
+            data: data.clone(),
+            last_use,
+        });
+        cache.trim(CACHE_BUDGET);
+        Ok(Cached {
+            data,
+            _p: PhantomData,
+        })
+    }
+
+    /// Evicts the least recently used unreferenced entries until they fit in `budget`
+    fn trim(&mut self, budget: usize) -> usize {
+        let mut unused: usize = self
+            .entries
+            .iter()
+            .filter(|entry| entry.unused())
+            .map(|entry| entry.data.len())
+            .sum();
+        let mut freed = 0;
+        while unused > budget {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.unused())
+                .min_by_key(|(_, entry)| entry.last_use)
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+            let entry = self.entries.swap_remove(oldest);
+            unused -= entry.data.len();
+            freed += entry.data.len();
+        }
+        freed
+    }
+}
 
-        let mut load = vec![0u8; SIZE];
-        let dest = load.as_mut_ptr().cast();
-        unsafe { LZ77UnCompWram(self.data.cast(), dest) };
-        AllocBuf::new(load.into_boxed_slice())
+/// Bytes held by the cache, referenced or not
+pub fn cached_bytes() -> usize {
+    let cache = RESOURCE_CACHE.borrow();
+    cache.entries.iter().map(|entry| entry.data.len()).sum()
+}
+
+/// Frees every cached resource no longer referenced, returning the bytes freed
+///
+/// Called by `Alloc_` when the heap is full, so it gives up if the
+/// cache is already being updated.
+pub fn evict_unused() -> usize {
+    match RESOURCE_CACHE.try_borrow_mut() {
+        Ok(mut cache) => cache.trim(0),
+        Err(_) => 0,
+    }
+}
+
+/// Evicts unreferenced resources past the budget, once a scene dropped the ones it used
+pub fn trim_cache() -> usize {
+    match RESOURCE_CACHE.try_borrow_mut() {
+        Ok(mut cache) => cache.trim(CACHE_BUDGET),
+        Err(_) => 0,
     }
 }
 
-#[macro_export]
-macro_rules! include_res_lz {
-    ($name:ident, $path:literal) => {
-        static $name: $crate::resources::CompressedResource<{ include_bytes!($path).len() }> =
-            $crate::resources::CompressedResource::from_ref(include_bytes!(concat!($path, ".lz")));
+/// [`evict_unused`] for the C side, `Alloc_` retries once if anything was freed
+#[unsafe(no_mangle)]
+extern "C" fn FlushResourceCache() -> u32 {
+    evict_unused() as u32
+}
+
+/// Tile data converted from a png by the build script, see [`crate::assets`]
+pub struct ImageResource<T, const SIZE: usize> {
+    pub width: u16,
+    pub height: u16,
+    data: CompressedResource<T, SIZE>,
+}
+
+impl<T, const SIZE: usize> ImageResource<T, SIZE> {
+    pub const fn new(width: u16, height: u16, data: CompressedResource<T, SIZE>) -> Self {
+        if (width as usize / 8) * (height as usize / 8) * size_of::<T>() != SIZE {
+            panic!("Image dimensions don't match its data")
+        }
+        ImageResource {
+            width,
+            height,
+            data,
+        }
+    }
+
+    pub const fn tiles(&self) -> Vec2D<u16> {
+        Vec2D::new(self.width / 8, self.height / 8)
+    }
+}
+
+impl<T, const SIZE: usize> Deref for ImageResource<T, SIZE> {
+    type Target = CompressedResource<T, SIZE>;
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+/// Tilemap of `WIDTH` by `HEIGHT` tiles, see [`crate::assets`]
+///
+/// The dimensions are part of the type so full screen tilemaps can be
+/// checked against their [`ScreenSize`] at compile time.
+unsafe impl<T, const W: usize, const H: usize> Sync for TilemapResource<T, W, H> {}
+pub struct TilemapResource<T, const WIDTH: usize, const HEIGHT: usize> {
+    data: *const u8,
+    _p: PhantomData<T>,
+}
+
+impl<T, const WIDTH: usize, const HEIGHT: usize> TilemapResource<T, WIDTH, HEIGHT> {
+    pub const SIZE: usize = WIDTH * HEIGHT * size_of::<T>();
+    pub const SCREEN_SIZE: ScreenSize = match ScreenSize::from_tiles(WIDTH, HEIGHT) {
+        Some(size) => size,
+        None => panic!("Tilemap dimensions don't match any screen size"),
     };
+
+    pub const fn from_ref(data: &'static [u8]) -> Self {
+        TilemapResource {
+            data: data.as_ptr(),
+            _p: PhantomData,
+        }
+    }
+
+    pub const fn dim(&self) -> Vec2D<u8> {
+        Vec2D::new(WIDTH as u8, HEIGHT as u8)
+    }
+
+    pub const fn rect(&self) -> Rect<u8> {
+        Rect::new(0, 0, WIDTH as u8, HEIGHT as u8)
+    }
+
+    pub fn load(&self) -> AllocBuf<T> {
+        decompress(self.data, Self::SIZE)
+    }
+
+    pub fn try_load(&self) -> Result<AllocBuf<T>, OutOfMemory> {
+        try_decompress(self.data, Self::SIZE)
+    }
+
+    pub fn load_cached(&self) -> Cached<T> {
+        self.try_load_cached()
+            .unwrap_or_else(|err| panic!("Couldn't load resource: {err:?}"))
+    }
+
+    pub fn try_load_cached(&self) -> Result<Cached<T>, OutOfMemory> {
+        match ResourceCache::lookup(self.data) {
+            Some(cached) => Ok(cached),
+            None => ResourceCache::insert(self.data, self.try_load()?),
+        }
+    }
 }
 
 pub unsafe fn static_deref<'a, T>(target: *mut T) -> &'a mut T {
@@ -0,0 +1,109 @@
+//! Stack depth tracking for the IWRAM stack
+//!
+//! The unused part of the stack is painted with a pattern when a scene
+//! starts, so the deepest point it reached can be found afterwards. The
+//! bottom of the painted area is a guard checked on every executor poll,
+//! which panics while there's still room left for the panic handler.
+
+use core::cell::Cell;
+use core::ffi::{CStr, c_void};
+
+use crate::info;
+use crate::pokeemerald::REG_ADDR_IME;
+use crate::ram::statics_end;
+
+const PAINT: u32 = 0xDEAD_BEEF;
+/// Bytes above the IWRAM statics that the stack should never reach
+const GUARD_SIZE: usize = 0x200;
+/// Bytes left unpainted below the frame of `paint`, for itself and interrupts
+const PAINT_MARGIN: usize = 0x40;
+
+unsafe extern "C" {
+    static gAgbMainLoop_sp: *const c_void;
+}
+
+struct Painted(Cell<bool>);
+unsafe impl Sync for Painted {}
+
+/// Whether the guard holds the pattern, nothing is checked before the first paint
+static PAINTED: Painted = Painted(Cell::new(false));
+
+/// Bytes used on the stack since the start of the main loop
+#[allow(unused)]
+#[inline(never)]
+pub fn stack_size() -> isize {
+    let mut offset = 0;
+    offset = unsafe { (gAgbMainLoop_sp).offset_from(&raw const offset as _) };
+    offset
+}
+
+fn guard() -> *mut u32 {
+    statics_end().next_multiple_of(4) as *mut u32
+}
+
+/// Paints the stack below the current frame, with interrupts off so none
+/// has a frame there while it's overwritten
+#[inline(never)]
+pub fn paint() {
+    let frame = 0u32;
+    let end = ((&raw const frame) as usize - PAINT_MARGIN) as *mut u32;
+    let ime = REG_ADDR_IME as *mut u16;
+    unsafe {
+        let saved = ime.read_volatile();
+        ime.write_volatile(0);
+        let mut word = guard();
+        while word < end {
+            word.write_volatile(PAINT);
+            word = word.add(1);
+        }
+        ime.write_volatile(saved);
+    }
+    PAINTED.0.set(true);
+}
+
+/// Deepest point reached since the last [`paint`], in bytes from the start of the main loop
+pub fn max_depth() -> usize {
+    let mut word = guard();
+    unsafe {
+        while word.read_volatile() == PAINT {
+            word = word.add(1);
+        }
+        (gAgbMainLoop_sp as usize).saturating_sub(word as usize)
+    }
+}
+
+/// Panics if the stack reached the guard since the last [`paint`]
+pub fn check() {
+    if !PAINTED.0.get() {
+        return;
+    }
+    let guard = guard();
+    let touched = (0..GUARD_SIZE / 4).any(|i| unsafe { guard.add(i).read_volatile() } != PAINT);
+    if touched {
+        panic!(
+            "Stack overflow: {} bytes deep, within {GUARD_SIZE} bytes of the IWRAM statics",
+            max_depth()
+        );
+    }
+}
+
+/// Stack usage of a scene, reported when it ends
+pub struct StackScope {
+    tag: &'static CStr,
+}
+
+impl StackScope {
+    pub fn enter(tag: &'static CStr) -> Self {
+        paint();
+        StackScope { tag }
+    }
+
+    pub fn exit(self) {
+        let available = (unsafe { gAgbMainLoop_sp } as usize).saturating_sub(guard() as usize);
+        info!(
+            "{:?} used {} of {available} bytes of stack",
+            self.tag,
+            max_depth()
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! The engine's string vars and `{PLAYER}`-style placeholders
+//!
+//! Text from C usually fills `gStringVar1..3` and then expands a template
+//! with `StringExpandPlaceholders` into `gStringVar4`. That writes with no
+//! bound, so [`expand`] walks the template here and only asks the engine for
+//! the text of each placeholder.
+
+use crate::charmap::{ArrayPkstr, EOS, PLACEHOLDER_BEGIN, Pkstr};
+use crate::pokeemerald::{
+    GetExpandedPlaceholder, gStringVar1, gStringVar2, gStringVar3, gStringVar4,
+};
+use crate::warn;
+
+/// Buffers shared with the C text code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringVar {
+    /// `{STR_VAR_1}`
+    Var1,
+    /// `{STR_VAR_2}`
+    Var2,
+    /// `{STR_VAR_3}`
+    Var3,
+    /// The usual destination of expanded text, with no placeholder
+    Var4,
+}
+
+impl StringVar {
+    fn buffer(self) -> *mut [u8] {
+        match self {
+            StringVar::Var1 => &raw mut gStringVar1,
+            StringVar::Var2 => &raw mut gStringVar2,
+            StringVar::Var3 => &raw mut gStringVar3,
+            StringVar::Var4 => &raw mut gStringVar4,
+        }
+    }
+
+    /// Copies `str`, cut after the last char or code that fits
+    pub fn set(self, str: &Pkstr) {
+        let buffer = self.buffer();
+        let (ptr, cap) = (buffer.cast::<u8>(), buffer.len());
+        let mut len = 0;
+        for code in str.codes() {
+            if len + code.len() >= cap {
+                warn!("{str:?} cut to fit in {self:?}");
+                break;
+            }
+            unsafe {
+                ptr.add(len)
+                    .copy_from_nonoverlapping(code.as_ptr(), code.len())
+            };
+            len += code.len();
+        }
+        unsafe { ptr.add(len).write(EOS) };
+    }
+
+    /// The current text, until the C code changes it
+    pub unsafe fn get<'a>(self) -> &'a Pkstr {
+        unsafe { Pkstr::from_eos_ptr(self.buffer().cast()) }
+    }
+}
+
+/// Copies `template` with its placeholders replaced, cut after the last char
+/// or code that fits
+pub fn expand<const CAP: usize>(template: &Pkstr) -> ArrayPkstr<CAP> {
+    let mut out = ArrayPkstr::new();
+    if !expand_into(&mut out, template) {
+        warn!("{template:?} cut to fit in {CAP} bytes");
+    }
+    out
+}
+
+fn expand_into<const CAP: usize>(out: &mut ArrayPkstr<CAP>, template: &Pkstr) -> bool {
+    template.codes().all(|code| match code {
+        &[PLACEHOLDER_BEGIN, id] => {
+            let text = unsafe { Pkstr::from_eos_ptr(GetExpandedPlaceholder(id as u32)) };
+            expand_into(out, text)
+        }
+        code => out.push_bytes(code),
+    })
+}
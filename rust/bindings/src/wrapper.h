@@ -15,8 +15,12 @@
 #include "palette.h"
 #include "party_menu.h"
 #include "pokemon.h"
+#include "pokemon_animation.h"
+#include "pokemon_icon.h"
 #include "pokemon_summary_screen.h"
+#include "sound.h"
 #include "sprite.h"
+#include "string_util.h"
 #include "task.h"
 #include "text_window.h"
 #include "trainer_pokemon_sprites.h"
@@ -0,0 +1,11 @@
+use std::env;
+use std::path::PathBuf;
+
+#[path = "../bindings/build/charmap.rs"]
+mod charmap;
+
+fn main() {
+    let manifest_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let output_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    charmap::build(&manifest_path.join("../../charmap.txt"), &output_path);
+}
@@ -0,0 +1,39 @@
+//! Host builds of the modules that don't touch the hardware, for `cargo test`
+//!
+//! The bindings crate only builds for the GBA, since its build script runs
+//! bindgen on the decomp headers. Run with `make -f rust/rust.mk host-test`,
+//! or `cargo test --manifest-path rust/host-tests/Cargo.toml` from the root of
+//! the repo, as the GBA config in `rust/.cargo` rebuilds `core`.
+
+#![allow(clippy::missing_safety_doc)]
+
+#[path = "../../bindings/src/charmap.rs"]
+pub mod charmap;
+#[path = "../../bindings/build/huffman.rs"]
+pub mod huffman;
+#[path = "../../bindings/src/log_ring.rs"]
+pub mod log_ring;
+#[path = "../../bindings/src/lz77.rs"]
+pub mod lz77;
+/// The compressor of the bindings build script
+#[path = "../../bindings/build/lz77.rs"]
+pub mod lz77_compress;
+/// The run-length compressor of the bindings build script
+#[path = "../../bindings/build/rl.rs"]
+pub mod rl_compress;
+
+/// Stands in for the log macro of the bindings crate, used by `pkformat!`
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        eprintln!($($arg)+)
+    };
+}
+
+/// Stands in for the IWRAM placement of the bindings crate, used by the LZ77 decoder
+#[macro_export]
+macro_rules! iwram {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
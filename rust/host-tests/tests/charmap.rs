@@ -0,0 +1,68 @@
+//! Strings encoded at compile time by `pkstr!`, decoded back with the tables
+//! generated from the decomp's `charmap.txt`
+
+use host_tests::charmap::{ArrayPkstr, Decoded, EOS, Pkstr};
+use host_tests::{pkformat, pkstr, try_pkformat};
+
+fn bytes(pkstr: &Pkstr) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(pkstr.as_ptr(), pkstr.len()) }
+}
+
+#[test]
+fn encodes_with_charmap_txt() {
+    assert_eq!(bytes(pkstr!("Poké")), [0xCA, 0xE3, 0xDF, 0x1B, EOS]);
+    assert_eq!(bytes(pkstr!("♂♀")), [0xB5, 0xB6, EOS]);
+    assert_eq!(bytes(pkstr!("{COLOR RED}")), [0xFC, 0x01, 0x04, EOS]);
+    assert_eq!(bytes(pkstr!("{FONT_SMALL}")), [0xFC, 0x06, 0x00, EOS]);
+    assert_eq!(bytes(pkstr!("{STR_VAR_1}")), [0xFD, 0x02, EOS]);
+}
+
+#[test]
+fn round_trips_accented_letters() {
+    let text = "Pokémon ÉCOLE àâçèêëîïôùûü";
+    assert_eq!(pkstr!("Pokémon ÉCOLE àâçèêëîïôùûü").to_string(), text);
+    assert_eq!(ArrayPkstr::<32>::new_str(text).to_string(), text);
+}
+
+#[test]
+fn round_trips_gender_symbols() {
+    assert_eq!(pkstr!("NIDORAN♂ NIDORAN♀").to_string(), "NIDORAN♂ NIDORAN♀");
+}
+
+#[test]
+fn round_trips_codes() {
+    assert_eq!(pkstr!("{COLOR 4}Hi").to_string(), "{COLOR 4}Hi");
+    assert_eq!(pkstr!("{FONT_SMALL}Lv.5").to_string(), "{FONT_SMALL}Lv.5");
+    assert_eq!(
+        pkstr!("{STR_VAR_1} grew to {STR_VAR_2}!").to_string(),
+        "{STR_VAR_1} grew to {STR_VAR_2}!"
+    );
+}
+
+#[test]
+fn decodes_code_arguments() {
+    let decoded: Vec<_> = pkstr!("{COLOR 4}♀").decode().collect();
+    assert_eq!(decoded, [Decoded::Code("COLOR", &[4]), Decoded::Char('♀')]);
+}
+
+#[test]
+fn encodes_missing_chars_as_question_marks() {
+    assert_eq!(ArrayPkstr::<8>::new_str("a€b").to_string(), "a?b");
+}
+
+#[test]
+fn formats_into_charmap_bytes() {
+    let formatted = pkformat!(8, "Lv{}♀", 42);
+    assert_eq!(formatted.to_string(), "Lv42♀");
+    assert_eq!(bytes(&formatted), bytes(pkstr!("Lv42♀")));
+}
+
+#[test]
+fn cuts_formatting_to_what_fits() {
+    assert_eq!(pkformat!(4, "Lv{}", 100).to_string(), "Lv1");
+    assert_eq!(pkformat!(8, "a€b").to_string(), "a");
+
+    let cut = try_pkformat!(4, "Lv{}", 100).unwrap_err();
+    assert_eq!(bytes(&cut), [0xC6, 0xEA, 0xA2, EOS]);
+    assert!(try_pkformat!(6, "Lv{}", 100).is_ok());
+}
@@ -0,0 +1,116 @@
+use host_tests::huffman;
+
+/// The BIOS `HuffUnComp`, ported from gbagfx's `HuffDecompress`
+fn decompress(src: &[u8]) -> Vec<u8> {
+    let bits = src[0] & 0xF;
+    assert_eq!(src[0] & 0xF0, 0x20, "Not Huffman data");
+    let size = u32::from_le_bytes([src[1], src[2], src[3], 0]) as usize;
+    let mut dest = Vec::with_capacity(size);
+
+    let mut tree_pos = 5;
+    let mut src_pos = 4 + (src[4] as usize + 1) * 2;
+    let mut word = 0u32;
+    let mut symbols = 0;
+    while dest.len() < size {
+        let mut window = u32::from_le_bytes(src[src_pos..src_pos + 4].try_into().unwrap());
+        src_pos += 4;
+        for _ in 0..32 {
+            let bit = (window >> 31) as usize;
+            let node = src[tree_pos];
+            let leaf = (node << bit) & 0x80 != 0;
+            tree_pos = (tree_pos & !1) + ((node & 0x3F) as usize + 1) * 2 + bit;
+            if leaf {
+                word = word >> bits | (src[tree_pos] as u32) << (32 - bits);
+                symbols += 1;
+                if symbols == 32 / bits {
+                    dest.extend_from_slice(&word.to_le_bytes());
+                    symbols = 0;
+                    if dest.len() == size {
+                        break;
+                    }
+                }
+                tree_pos = 5;
+            }
+            window <<= 1;
+        }
+    }
+    dest
+}
+
+/// xorshift, to get the same data on every run
+fn random(seed: &mut u32) -> u32 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    *seed
+}
+
+/// Symbols with Zipf frequencies, like the colors of most graphics
+fn zipf(symbols: u32, len: usize, seed: &mut u32) -> Vec<u32> {
+    let weights: Vec<f64> = (1..=symbols).map(|rank| 1.0 / rank as f64).collect();
+    let total: f64 = weights.iter().sum();
+    (0..len)
+        .map(|_| {
+            let mut pick = random(seed) as f64 / u32::MAX as f64 * total;
+            weights
+                .iter()
+                .position(|&weight| {
+                    pick -= weight;
+                    pick <= 0.0
+                })
+                .unwrap_or(symbols as usize - 1) as u32
+        })
+        .collect()
+}
+
+fn round_trip(data: &[u8], bits: u8) {
+    let compressed = huffman::compress(data, bits);
+    assert_eq!(compressed.len() % 4, 0);
+    assert_eq!(decompress(&compressed), data);
+}
+
+#[test]
+fn round_trips_4bpp() {
+    let mut seed = 0x1234_5678;
+    let symbols = zipf(16, 0x800, &mut seed);
+    let data: Vec<u8> = symbols
+        .chunks(2)
+        .map(|pair| (pair[0] | pair[1] << 4) as u8)
+        .collect();
+    round_trip(&data, 4);
+}
+
+#[test]
+fn round_trips_8bpp() {
+    // Every symbol used, for a wide tree
+    let mut seed = 0x8765_4321;
+    let data: Vec<u8> = (0..256)
+        .chain(zipf(256, 0x8000, &mut seed))
+        .map(|symbol| symbol as u8)
+        .collect();
+    round_trip(&data, 8);
+}
+
+#[test]
+fn round_trips_random_frequencies() {
+    // Some of these trees were too wide to lay out breadth first
+    let mut seed = 0xC0FF_EE01;
+    for _ in 0..200 {
+        let used = 2 + random(&mut seed) % 255;
+        let len = 4 * (1 + random(&mut seed) as usize % 0x400);
+        let data: Vec<u8> = (0..len)
+            .map(|_| {
+                // Squared to skew the frequencies
+                let pick = random(&mut seed) % used;
+                (pick * pick / used) as u8
+            })
+            .collect();
+        round_trip(&data, 8);
+    }
+}
+
+#[test]
+fn round_trips_a_single_symbol() {
+    round_trip(&[0x11; 8], 4);
+    round_trip(&[0xAB; 8], 8);
+}
@@ -0,0 +1,76 @@
+//! The ring buffer of recent log lines, once it's full
+
+use host_tests::log_ring::{LINE_SIZE, Level, RingBuffer};
+
+fn lines<const SIZE: usize>(ring: &RingBuffer<SIZE>) -> Vec<(Level, String)> {
+    let mut lines = Vec::new();
+    ring.for_each(|level, line| lines.push((level, line.to_string())));
+    lines
+}
+
+#[test]
+fn keeps_lines_in_order() {
+    let mut ring = RingBuffer::<64>::new();
+    ring.push(Level::Info, "first");
+    ring.push(Level::Warn, "second");
+    assert_eq!(
+        lines(&ring),
+        [
+            (Level::Info, "first".into()),
+            (Level::Warn, "second".into())
+        ]
+    );
+}
+
+#[test]
+fn wraps_past_the_end() {
+    // Records are 2 bytes of header and 8 of text, so the 4th one wraps around
+    let mut ring = RingBuffer::<32>::new();
+    for i in 0..10 {
+        ring.push(Level::Debug, &format!("line {i:03}"));
+    }
+    let expected: Vec<_> = (7..10)
+        .map(|i| (Level::Debug, format!("line {i:03}")))
+        .collect();
+    assert_eq!(lines(&ring), expected);
+}
+
+#[test]
+fn evicts_several_lines_for_a_long_one() {
+    let mut ring = RingBuffer::<32>::new();
+    for line in ["a", "b", "c", "d", "e"] {
+        ring.push(Level::Info, line);
+    }
+    ring.push(Level::Error, &"x".repeat(24));
+    assert_eq!(
+        lines(&ring),
+        [
+            (Level::Info, "d".into()),
+            (Level::Info, "e".into()),
+            (Level::Error, "x".repeat(24)),
+        ]
+    );
+    assert_eq!(ring.lines(), 3);
+}
+
+#[test]
+fn truncates_lines_to_line_size() {
+    let mut ring = RingBuffer::<0x400>::new();
+    ring.push(Level::Warn, &"y".repeat(LINE_SIZE + 10));
+    ring.push(Level::Info, "after");
+    assert_eq!(
+        lines(&ring),
+        [
+            (Level::Warn, "y".repeat(LINE_SIZE)),
+            (Level::Info, "after".into())
+        ]
+    );
+}
+
+#[test]
+fn truncates_lines_to_the_buffer() {
+    let mut ring = RingBuffer::<16>::new();
+    ring.push(Level::Info, "before");
+    ring.push(Level::Trace, &"z".repeat(20));
+    assert_eq!(lines(&ring), [(Level::Trace, "z".repeat(14))]);
+}
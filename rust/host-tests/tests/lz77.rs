@@ -0,0 +1,65 @@
+//! The LZ77 decoder against files compressed by gbagfx, as the decomp does
+//! for its graphics
+//!
+//! The files in `data` were made from `graphics/` with `tools/gbagfx/gbagfx`,
+//! first from .png to .4bpp, then from there to .lz.
+
+use host_tests::lz77::{self, Lz77Decoder};
+use host_tests::lz77_compress;
+
+fn decompress(src: &[u8]) -> Vec<u8> {
+    let size = u32::from_le_bytes(src[..4].try_into().unwrap()) as usize >> 8;
+    let mut dest = vec![0; size];
+    unsafe { lz77::decompress(src.as_ptr(), &mut dest) };
+    dest
+}
+
+#[test]
+fn decodes_gbagfx_tiles() {
+    let compressed = include_bytes!("data/party_menu_bg.4bpp.lz");
+    assert_eq!(decompress(compressed), include_bytes!("data/party_menu_bg.4bpp"));
+
+    let compressed = include_bytes!("data/bulbasaur_anim_front.4bpp.lz");
+    let expected = include_bytes!("data/bulbasaur_anim_front.4bpp");
+    assert_eq!(decompress(compressed), expected);
+}
+
+#[test]
+fn decodes_gbagfx_tilemap() {
+    let compressed = include_bytes!("data/party_menu_bg.bin.lz");
+    let expected = include_bytes!("../../../graphics/party_menu/bg.bin");
+    assert_eq!(decompress(compressed), expected);
+}
+
+#[test]
+fn decodes_incrementally() {
+    let compressed = include_bytes!("data/bulbasaur_anim_front.4bpp.lz");
+    let mut decoder = unsafe { Lz77Decoder::new(compressed.as_ptr()) };
+    let mut dest = vec![0; decoder.len()];
+    let mut calls = 1;
+    while !decoder.decode(&mut dest, 0x40) {
+        calls += 1;
+    }
+    assert!(calls > 1);
+    assert_eq!(dest, include_bytes!("data/bulbasaur_anim_front.4bpp"));
+}
+
+#[test]
+fn decodes_the_build_script_compressor() {
+    let data = include_bytes!("data/bulbasaur_anim_front.4bpp");
+    assert_eq!(decompress(&lz77_compress::compress(data)), data);
+}
+
+#[test]
+#[should_panic(expected = "LZ77 match 3 bytes back, after 1 bytes")]
+fn rejects_matches_before_the_start() {
+    // 4 bytes: a literal, then a match of 3 bytes from 3 bytes back
+    let compressed = [0x10, 4, 0, 0, 0b0100_0000, 0xAA, 0x00, 0x02];
+    decompress(&compressed);
+}
+
+#[test]
+#[should_panic(expected = "Not LZ77 data")]
+fn rejects_other_formats() {
+    decompress(&[0x30, 4, 0, 0, 0x83, 0xAA]);
+}
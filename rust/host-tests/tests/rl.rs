@@ -0,0 +1,93 @@
+//! The run-length compressor of the build script, against a port of the BIOS
+//! decoder, around the limits of the block lengths
+
+use host_tests::rl_compress::compress;
+
+/// The BIOS `RLUnComp`, ported from gbagfx's `RLDecompress`
+fn decompress(src: &[u8]) -> Vec<u8> {
+    assert_eq!(src[0], 0x30, "Not run-length data");
+    let size = u32::from_le_bytes([src[1], src[2], src[3], 0]) as usize;
+    let mut dest = Vec::with_capacity(size);
+
+    let mut pos = 4;
+    while dest.len() < size {
+        let flag = src[pos];
+        pos += 1;
+        match flag & 0x80 != 0 {
+            true => {
+                let len = (flag & 0x7F) as usize + 3;
+                dest.extend(std::iter::repeat_n(src[pos], len));
+                pos += 1;
+            }
+            false => {
+                let len = flag as usize + 1;
+                dest.extend_from_slice(&src[pos..pos + len]);
+                pos += len;
+            }
+        }
+    }
+    assert_eq!(dest.len(), size, "Block past the end of the data");
+    dest
+}
+
+/// Block flags of `compressed`, skipping their data
+fn blocks(compressed: &[u8]) -> Vec<u8> {
+    let size = u32::from_le_bytes([compressed[1], compressed[2], compressed[3], 0]) as usize;
+    let (mut pos, mut written, mut flags) = (4, 0, Vec::new());
+    while written < size {
+        let flag = compressed[pos];
+        flags.push(flag);
+        let (len, skip) = match flag & 0x80 != 0 {
+            true => ((flag & 0x7F) as usize + 3, 1),
+            false => (flag as usize + 1, flag as usize + 1),
+        };
+        pos += 1 + skip;
+        written += len;
+    }
+    flags
+}
+
+/// Literal bytes that never repeat 3 times in a row
+fn literals(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+}
+
+fn round_trip(data: &[u8]) -> Vec<u8> {
+    let compressed = compress(data);
+    assert_eq!(compressed.len() % 4, 0);
+    assert_eq!(decompress(&compressed), data);
+    blocks(&compressed)
+}
+
+#[test]
+fn encodes_runs_from_3_bytes() {
+    assert_eq!(round_trip(&[7; 3]), [0x80]);
+    assert_eq!(round_trip(&[1, 7, 7, 2]), [0x03]);
+    assert_eq!(round_trip(&[1, 7, 7, 7, 2]), [0x00, 0x80, 0x00]);
+}
+
+#[test]
+fn splits_runs_over_130_bytes() {
+    assert_eq!(round_trip(&[7; 130]), [0xFF]);
+    assert_eq!(round_trip(&[7; 131]), [0xFF, 0x00]);
+    assert_eq!(round_trip(&[7; 133]), [0xFF, 0x80]);
+    assert_eq!(round_trip(&[7; 260]), [0xFF, 0xFF]);
+}
+
+#[test]
+fn splits_literals_over_128_bytes() {
+    assert_eq!(round_trip(&literals(128)), [0x7F]);
+    assert_eq!(round_trip(&literals(129)), [0x7F, 0x00]);
+    assert_eq!(round_trip(&literals(256)), [0x7F, 0x7F]);
+}
+
+#[test]
+fn ends_with_partial_blocks() {
+    let mut data = literals(200);
+    data.extend([9; 5]);
+    data.extend([1, 2]);
+    assert_eq!(round_trip(&data), [0x7F, 0x47, 0x82, 0x01]);
+
+    assert_eq!(round_trip(&[1]), [0x00]);
+    assert_eq!(round_trip(&[]), []);
+}
@@ -1,26 +1,17 @@
 RUST_DIR := $(dir $(lastword $(MAKEFILE_LIST)))
-PARTY_GRAPHICS := $(RUST_DIR)/graphics/party_menu_full
 CPPFLAGS += -iquote $(RUST_DIR)/include
 
-$(PARTY_GRAPHICS)/tera/tera_types.gbapal:\
-		$(PARTY_GRAPHICS)/tera/tera_types_1.gbapal\
-		$(PARTY_GRAPHICS)/tera/tera_types_2.gbapal\
-		$(PARTY_GRAPHICS)/tera/tera_types_3.gbapal
-	@cat $^ > $@
+librust:
+	cd $(RUST_DIR) && cargo build --release
 
-$(PARTY_GRAPHICS)/tera/tera_types.4bpp: $(types:%=$(PARTY_GRAPHICS)/tera/%.4bpp)
-	@cat $^ > $@
+# Sizes of the IWRAM sections, then the largest symbols placed there
+iwram-report: $(ELF)
+	@$(PREFIX)size -A -x $(ELF) | grep -e section -e iwram
+	@$(PREFIX)nm -S --size-sort -r $(ELF) | awk '$$1 ~ /^030/' | head -n 20
 
-RUST_INC_FILES :=\
-	$(PARTY_GRAPHICS)/bg.bin.lz\
-	$(PARTY_GRAPHICS)/tiles.gbapal.lz\
-	$(PARTY_GRAPHICS)/tiles.4bpp.lz\
-	$(PARTY_GRAPHICS)/mon_bg.bin.lz\
-	$(PARTY_GRAPHICS)/hp.plain.bin.lz\
-	$(PARTY_GRAPHICS)/status_icons.4bpp.lz\
-	$(PARTY_GRAPHICS)/status_icons.gbapal.lz\
-	$(PARTY_GRAPHICS)/tera/tera_types.4bpp.lz\
-	$(PARTY_GRAPHICS)/tera/tera_types.gbapal.lz
+# Tests of the modules that also build for the host, run from outside of
+# rust/ so its .cargo/config.toml doesn't build core for the GBA
+host-test:
+	cargo test --manifest-path $(RUST_DIR)host-tests/Cargo.toml
 
-librust: $(RUST_INC_FILES)
-	cd $(RUST_DIR) && cargo build --release
\ No newline at end of file
+.PHONY: iwram-report host-test
@@ -1,59 +1,34 @@
 use alloc::boxed::Box;
-use alloc::vec;
 use core::cmp::min;
-use core::mem::swap;
 
 use arrayvec::ArrayVec;
-use bindings::charmap::{ArrayPkstr, Pkstr};
+use bindings::arena::Arena;
+use bindings::assets::party_menu_full::tera::{
+    TERA_TYPES_4BPP as TERA_SPRITE, TERA_TYPES_GBAPAL as TERA_SPRITE_PAL,
+};
+use bindings::assets::party_menu_full::{
+    BG_BIN as SCROLL_BG_MAP, HP_PLAIN_BIN as HP_MAP, MON_BG_BIN as MON_BG_MAP,
+    STATUS_ICONS_4BPP as STATUS_SHEET, STATUS_ICONS_GBAPAL as STATUS_PAL, TILES_4BPP as TILESET,
+    TILES_GBAPAL as PAL,
+};
+use bindings::charmap::Pkstr;
 use bindings::data::{Pokemon, get_item};
 use bindings::future::{Executor, RefCellSync, sleep};
 use bindings::graphics::{ListMenu, Sprite, SpriteSheet, Tileset, Window, *};
+use bindings::heap::{OutOfMemory, log_heap_stats, try_alloc_bytes};
 use bindings::input::Button;
 use bindings::pokeemerald::{self, *};
-use bindings::resources::{AllocBuf, Buffer, lz_ptr_res, static_deref};
-use bindings::{aformat, include_res_lz, mgba_warn, pkstr};
+use bindings::ram::log_iwram_usage;
+use bindings::resources::{AllocBuf, Buffer, Cached, lz_ptr_res, static_deref};
+use bindings::{anim_table, pkformat, pkstr, trace};
 use derive_more::TryFrom;
 
 static EXECUTOR: Executor = Executor::new();
 static STORED_CALLBACK: RefCellSync<MainCallback> = RefCellSync::new(None);
 static SELECTED_POKE: RefCellSync<u8> = RefCellSync::new(0);
 
-const TYPE_ANIMS: &[*const AnimCmd] = &[
-    [anim_frame(0, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(4, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(8, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(12, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(16, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(20, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(24, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(28, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(32, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(36, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(40, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(44, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(48, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(52, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(56, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(60, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(64, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(68, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(72, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(76, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(80, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(84, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(88, 0, false, false), anim_end()].as_ptr(),
-];
-
-const STATUS_ANIMS: &[*const AnimCmd] = &[
-    [anim_frame(0, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(4, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(8, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(12, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(16, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(20, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(24, 0, false, false), anim_end()].as_ptr(),
-    [anim_frame(28, 0, false, false), anim_end()].as_ptr(),
-];
+anim_table!(static TYPE_ANIMS = tile_offsets(23, 4));
+anim_table!(static STATUS_ANIMS = tile_offsets(8, 4));
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
@@ -70,17 +45,13 @@ extern "C" fn InitFullSummaryScreen(
     pokemons: *mut pokeemerald::Pokemon,
     count: usize,
 ) {
-    let fut = Box::new(party_screen(back, style, pokemons, count, 0));
-    mgba_warn!(
-        "heap: {:?} fut:{:?}-{:x?}",
-        &raw const gHeap,
-        &raw const *fut,
-        (&raw const *fut as usize) + size_of_val(&*fut)
-    );
+    let fut = party_screen(back, style, pokemons, count, 0);
+    log_heap_stats();
+    log_iwram_usage();
 
     unsafe { SetMainCallback2(Some(main_cb)) }
     *STORED_CALLBACK.borrow_mut() = back;
-    EXECUTOR.set(fut);
+    EXECUTOR.set_scene(c"party_screen", back, fut);
 }
 
 extern "C" fn return_from_party_callback() {
@@ -88,15 +59,9 @@ extern "C" fn return_from_party_callback() {
     let back = *STORED_CALLBACK.borrow();
     let count = unsafe { gPlayerPartyCount };
     let mons = &raw mut gPlayerParty;
-    let fut = Box::new(party_screen(
-        back,
-        Style::Party,
-        mons.cast(),
-        count as usize,
-        index,
-    ));
+    let fut = party_screen(back, Style::Party, mons.cast(), count as usize, index);
     unsafe { SetMainCallback2(Some(main_cb)) }
-    EXECUTOR.set(fut);
+    EXECUTOR.set_scene(c"party_screen", back, fut);
 }
 
 extern "C" fn return_from_give_hold_item_callback() {
@@ -112,15 +77,9 @@ extern "C" fn return_from_give_hold_item_callback() {
     let back = *STORED_CALLBACK.borrow();
     let count = unsafe { gPlayerPartyCount };
     let mons = &raw mut gPlayerParty;
-    let fut = Box::new(party_screen(
-        back,
-        Style::Party,
-        mons.cast(),
-        count as usize,
-        index,
-    ));
+    let fut = party_screen(back, Style::Party, mons.cast(), count as usize, index);
     unsafe { SetMainCallback2(Some(main_cb)) }
-    EXECUTOR.set(fut);
+    EXECUTOR.set_scene(c"party_screen", back, fut);
 }
 
 extern "C" fn main_cb() {
@@ -128,6 +87,7 @@ extern "C" fn main_cb() {
     unsafe {
         AnimateSprites();
         BuildOamBuffer();
+        schedule_dirty_tilemaps();
         DoScheduledBgTilemapCopiesToVram();
         UpdatePaletteFade();
     }
@@ -145,44 +105,20 @@ extern "C" fn vblank_cb() {
 
 const MON_POS: [(u8, u8); 6] = [(1, 1), (11, 1), (21, 1), (1, 10), (11, 10), (21, 10)];
 
-include_res_lz!(TILESET, "../graphics/party_menu_full/tiles.4bpp");
-include_res_lz!(PAL, "../graphics/party_menu_full/tiles.gbapal");
-include_res_lz!(SCROLL_BG_MAP, "../graphics/party_menu_full/bg.bin");
-include_res_lz!(MON_BG_MAP, "../graphics/party_menu_full/mon_bg.bin");
-include_res_lz!(HP_MAP, "../graphics/party_menu_full/hp.plain.bin");
-
-include_res_lz!(
-    TERA_SPRITE,
-    "../graphics/party_menu_full/tera/tera_types.4bpp"
-);
-include_res_lz!(
-    TERA_SPRITE_PAL,
-    "../graphics/party_menu_full/tera/tera_types.gbapal"
-);
-
-include_res_lz!(
-    STATUS_SHEET,
-    "../graphics/party_menu_full/status_icons.4bpp"
-);
-
-include_res_lz!(
-    STATUS_PAL,
-    "../graphics/party_menu_full/status_icons.gbapal"
-);
-
 type OwnedSprite = Sprite<AllocBuf<TileBitmap4bpp>>;
-async fn item_sprite(poke: &Pokemon, index: usize) -> Option<OwnedSprite> {
-    let item = poke.item()?;
+async fn item_sprite(poke: &Pokemon, index: usize) -> Result<Option<OwnedSprite>, OutOfMemory> {
+    let Some(item) = poke.item() else {
+        return Ok(None);
+    };
     let item_info = get_item(item);
-    let palette = lz_ptr_res::<{ 2 * 16 }>(item_info.iconPalette.cast());
-    let palette = load_obj_palette(6 + index as u8, &palette.load().get());
+    let palette = lz_ptr_res::<u16, { 2 * 16 }>(item_info.iconPalette.cast());
+    let palette = load_obj_palette(6 + index as u8, &palette.try_load()?.get());
 
     const ICON_SIZE: usize = size_of::<TileBitmap4bpp>() * 3 * 3;
     const SPRITE_SIZE: usize = size_of::<TileBitmap4bpp>() * 4 * 4;
 
-    let icon: AllocBuf<TileBitmap4bpp> = lz_ptr_res::<ICON_SIZE>(item_info.iconPic.cast()).load();
-    let sprite_buffer = vec![0; SPRITE_SIZE].into_boxed_slice();
-    let sprite_buffer: AllocBuf<TileBitmap4bpp> = AllocBuf::new(sprite_buffer);
+    let icon = lz_ptr_res::<TileBitmap4bpp, ICON_SIZE>(item_info.iconPic.cast()).try_load()?;
+    let sprite_buffer: AllocBuf<TileBitmap4bpp> = AllocBuf::new(try_alloc_bytes(SPRITE_SIZE)?);
     sleep(1).await;
 
     unsafe { CopyItemIconPicTo4x4Buffer(icon.as_ptr().cast(), sprite_buffer.as_mut_ptr().cast()) };
@@ -192,58 +128,51 @@ async fn item_sprite(poke: &Pokemon, index: usize) -> Option<OwnedSprite> {
     };
     sleep(1).await;
 
-    let sprite = Sprite::load(image, DUMMY_SPRITE_ANIMS, palette).await;
+    let sprite = Sprite::try_load(image, DUMMY_SPRITE_ANIMS, palette).await?;
     sprite.handle().set_priority(2);
-    Some(sprite)
+    Ok(Some(sprite))
 }
 
 fn type_palette(pktype: u16) -> ObjPalette {
     unsafe { ObjPalette::raw(gTypesInfo[pktype as usize].palette) }
 }
 
-fn load_type_palettes() {
-    load_obj_palette(13, &TERA_SPRITE_PAL.load().get());
+fn load_type_palettes() -> Result<(), OutOfMemory> {
+    load_obj_palette(13, &TERA_SPRITE_PAL.try_load_cached()?.get());
+    Ok(())
 }
 
 type TeraSprite<'a> = SheetSprite<'a>;
 fn tera_sprite<'a>(
     poke: &Pokemon,
-    sheet: &'a SpriteSheet<AllocBuf<TileBitmap4bpp>>,
-) -> Option<TeraSprite<'a>> {
+    sheet: &'a SpriteSheet<Cached<TileBitmap4bpp>>,
+) -> Result<Option<TeraSprite<'a>>, OutOfMemory> {
     if poke.is_egg() {
-        return None;
+        return Ok(None);
     }
 
     let tera = poke.tera_type();
-    let anims = SpriteAnims {
-        anims: TYPE_ANIMS.as_ptr(),
-        ..DUMMY_SPRITE_ANIMS
-    };
-    let sprite = SheetSprite::load(sheet, anims, type_palette(tera));
+    let sprite = SheetSprite::try_load(sheet, TYPE_ANIMS.sprite_anims(), type_palette(tera))?;
     sprite.start_animation(tera as u8);
     sprite.set_priority(2);
     sprite.set_subpriority(1);
-    Some(sprite)
+    Ok(Some(sprite))
 }
 
 fn status_sprite<'a>(
     poke: &Pokemon,
-    sheet: &'a SpriteSheet<AllocBuf<TileBitmap4bpp>>,
+    sheet: &'a SpriteSheet<Cached<TileBitmap4bpp>>,
     pal: ObjPalette,
-) -> Option<SheetSprite<'a>> {
+) -> Result<Option<SheetSprite<'a>>, OutOfMemory> {
     let status = poke.status();
     if status == 0 {
-        return None;
+        return Ok(None);
     }
 
-    let anims = SpriteAnims {
-        anims: STATUS_ANIMS.as_ptr(),
-        ..DUMMY_SPRITE_ANIMS
-    };
-    let sprite = SheetSprite::load(sheet, anims, pal);
+    let sprite = SheetSprite::try_load(sheet, STATUS_ANIMS.sprite_anims(), pal)?;
     sprite.start_animation(poke.status() - 1);
     sprite.set_priority(2);
-    Some(sprite)
+    Ok(Some(sprite))
 }
 
 enum BackgroundStyle {
@@ -268,13 +197,17 @@ impl BackgroundStyle {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntrySprite {
+    Pokemon,
+    Item,
+    Tera,
+    Status,
+}
+
 struct Entry<'a> {
-    index: u8,
     poke: Pokemon,
-    sprite: PokemonSpritePic,
-    item_sprite: Option<OwnedSprite>,
-    tera_sprite: Option<TeraSprite<'a>>,
-    status_sprite: Option<SheetSprite<'a>>,
+    sprites: SpriteGroup<'a, EntrySprite>,
     fg_title_window: Window,
     fg_hp_window: Window,
     bg_rect: Rect<u8>,
@@ -290,8 +223,23 @@ impl<'a> Entry<'a> {
 
     fn print_info(&self, resource: &Resources) {
         const HP_BAR_RECT: Rect<u8> = Rect::new(0, 3, 9, 1);
-        const HP_FILL_RECT_1: Rect<u16> = Rect::new(16, 27, 48, 1);
-        const HP_FILL_RECT_2: Rect<u16> = Rect::new(16, 28, 48, 2);
+        const HP_FILL_RECT: Rect<u16> = Rect::new(16, 27, 48, 3);
+        const HP_STYLE: GaugeStyle = GaugeStyle::Shaded { highlight: 1 };
+        const HP_EMPTY: GaugeColor = GaugeColor::new(8, 7);
+        const HP_THRESHOLDS: &[GaugeThreshold] = &[
+            GaugeThreshold {
+                percent: 25,
+                color: GaugeColor::new(15, 14),
+            },
+            GaugeThreshold {
+                percent: 50,
+                color: GaugeColor::new(11, 10),
+            },
+            GaugeThreshold {
+                percent: 100,
+                color: GaugeColor::new(13, 12),
+            },
+        ];
 
         let fg_title = &self.fg_title_window;
         let fg_hp = &self.fg_hp_window;
@@ -308,8 +256,7 @@ impl<'a> Entry<'a> {
             return;
         }
 
-        let lv = aformat!(5, "Lv{}", self.poke.level());
-        let lv = ArrayPkstr::<6>::new_str(&lv);
+        let lv = pkformat!(6, "Lv{}", self.poke.level());
         let lv_pos = Vec2D::new(69 - font.width_for(&lv) as u8, 0);
         fg_title.print_text(&lv, lv_pos, font);
 
@@ -318,27 +265,12 @@ impl<'a> Entry<'a> {
             &resource.hp_bar_map.get(),
             HP_BAR_RECT,
         );
-        let hp = aformat!(10, "{:<3}/{:<3}", self.poke.hp(), self.poke.max_hp());
-        let hp = ArrayPkstr::<11>::new_str(&hp);
+        let hp = pkformat!(11, "{:<3}/{:<3}", self.poke.hp(), self.poke.max_hp());
         fg_hp.print_text(&hp, Vec2D { x: 3, y: 13 }, font);
 
-        let width = self.poke.hp() * HP_FILL_RECT_1.width / self.poke.max_hp();
-        let (color1, color2) = match width {
-            0..12 => (15, 14),
-            12..24 => (11, 10),
-            _ => (13, 12),
-        };
-        let rect1 = Rect {
-            width,
-            ..HP_FILL_RECT_1
-        };
-        fg_hp.fill_rect(color1, rect1);
-
-        let rect2 = Rect {
-            width,
-            ..HP_FILL_RECT_2
-        };
-        fg_hp.fill_rect(color2, rect2);
+        let mut gauge = Gauge::new(HP_FILL_RECT, HP_STYLE, HP_EMPTY, HP_THRESHOLDS);
+        gauge.set(self.poke.hp(), self.poke.max_hp());
+        gauge.draw(fg_hp);
         fg_hp.copy_to_vram();
     }
 
@@ -351,26 +283,13 @@ impl<'a> Entry<'a> {
         };
         let pal_index = bg_type.palette_index();
         let bg_palette = resources.bg_palettes[pal_index];
-        mgba_warn!("update bg pal: {:?}", bg_palette);
+        trace!("update bg pal: {:?}", bg_palette);
         let tiles = &resources.mon_slot_map.get();
         let src_rect = Rect::from_vecs(Vec2D::new(0, 0), Self::BG_DIM);
         bg.copy_tile_rect(tiles, src_rect, self.bg_rect, Some(bg_palette));
         bg.schedule_copy_tilemap();
     }
 
-    fn update_pos(&mut self, pos: Vec2D<i16>) {
-        self.sprite.handle().set_pos(pos + Entry::POKE_SPRITE_OFFS);
-        if let Some(item_sprite) = &self.item_sprite {
-            item_sprite.handle().set_pos(pos + Entry::ITEM_SPRITE_OFFS);
-        }
-        if let Some(tera_sprite) = &self.tera_sprite {
-            tera_sprite.set_pos(pos + Entry::TERA_SPRITE_OFFS);
-        }
-        if let Some(status_sprite) = &self.status_sprite {
-            status_sprite.set_pos(pos + Entry::STATUS_SPRITE_OFFS);
-        }
-    }
-
     async fn switch(&mut self, other: &mut Entry<'a>, frames: i16) {
         self.fg_title_window.fill(0);
         self.fg_title_window.copy_to_vram();
@@ -382,22 +301,7 @@ impl<'a> Entry<'a> {
         other.fg_hp_window.copy_to_vram();
         sleep(1).await;
 
-        let pos_self = MON_POS[self.index as usize];
-        let pos_self = Vec2D::new(pos_self.0, pos_self.1).tile_to_pixel();
-        let pos_other = MON_POS[other.index as usize];
-        let pos_other = Vec2D::new(pos_other.0, pos_other.1).tile_to_pixel();
-        for i in 1..=frames {
-            let fpos_self = pos_other * i / frames + pos_self * (frames - i) / frames;
-            let fpos_other = pos_self * i / frames + pos_other * (frames - i) / frames;
-            self.update_pos(fpos_self);
-            other.update_pos(fpos_other);
-            sleep(1).await
-        }
-
-        swap(&mut self.status_sprite, &mut other.status_sprite);
-        swap(&mut self.tera_sprite, &mut other.tera_sprite);
-        swap(&mut self.item_sprite, &mut other.item_sprite);
-        swap(&mut self.sprite, &mut other.sprite);
+        self.sprites.swap(&mut other.sprites, frames).await;
         Pokemon::swap(&mut self.poke, &mut other.poke);
     }
 
@@ -407,7 +311,7 @@ impl<'a> Entry<'a> {
         bg: BgHandle<'_>,
         fg: BgHandle<'_>,
         index: u8,
-    ) -> Entry<'a> {
+    ) -> Result<Entry<'a>, OutOfMemory> {
         const FG_TITLE_DIM: Vec2D<u8> = Vec2D::new(9, 2);
         const FG_HP_DIM: Vec2D<u8> = Vec2D::new(9, 4);
         const FG_HP_POS: Vec2D<u8> = Vec2D::new(0, 4);
@@ -418,21 +322,30 @@ impl<'a> Entry<'a> {
         let (tile_x, tile_y) = MON_POS[index as usize];
         let tile_pos = Vec2D::new(tile_x, tile_y);
 
-        let mut sprite = PokemonSpritePic::new(&poke, index);
-        sprite.handle().set_priority(2);
+        let mut sprites = SpriteGroup::new(tile_pos.tile_to_pixel());
+        let sprite = PokemonSpritePic::try_new(&poke, index)?;
+        sprite.sprite().set_priority(2);
+        sprites.insert(EntrySprite::Pokemon, sprite, Self::POKE_SPRITE_OFFS);
         sleep(1).await;
-        let tera_sprite = tera_sprite(&poke, &resources.tera_sheet);
-        let item_sprite = item_sprite(&poke, index.into()).await;
-        let status_sprite = status_sprite(&poke, &resources.status_sheet, resources.status_pal);
+        if let Some(sprite) = tera_sprite(&poke, &resources.tera_sheet)? {
+            sprites.insert(EntrySprite::Tera, sprite, Self::TERA_SPRITE_OFFS);
+        }
+        if let Some(sprite) = item_sprite(&poke, index.into()).await? {
+            sprites.insert(EntrySprite::Item, sprite, Self::ITEM_SPRITE_OFFS);
+        }
+        let status_sprite = status_sprite(&poke, &resources.status_sheet, resources.status_pal)?;
+        if let Some(sprite) = status_sprite {
+            sprites.insert(EntrySprite::Status, sprite, Self::STATUS_SPRITE_OFFS);
+        }
 
         let block = BLOCK_SIZE * index as u16 + BASE_BLOCK;
         let rect = Rect::from_vecs(tile_pos, FG_TITLE_DIM);
-        let fg_title_window = Window::create(fg, rect, resources.bg_palettes[0], block);
+        let fg_title_window = Window::try_create(fg, rect, resources.bg_palettes[0], block)?;
         fg_title_window.put_tilemap();
 
         let block = block + FG_TITLE_DIM.size() as u16;
         let rect = Rect::from_vecs(tile_pos + FG_HP_POS, FG_HP_DIM);
-        let fg_hp_window = Window::create(fg, rect, resources.bg_palettes[0], block);
+        let fg_hp_window = Window::try_create(fg, rect, resources.bg_palettes[0], block)?;
         fg_hp_window.put_tilemap();
 
         let src_rect = Rect::from_vecs(Vec2D::new(0, 0), Self::BG_DIM);
@@ -440,19 +353,13 @@ impl<'a> Entry<'a> {
         bg.copy_tile_rect(&resources.mon_slot_map.get(), src_rect, bg_rect, None);
         sleep(1).await;
 
-        let mut entry = Entry {
-            index,
+        Ok(Entry {
             poke,
-            sprite,
-            item_sprite,
-            tera_sprite,
-            status_sprite,
+            sprites,
             bg_rect,
             fg_title_window,
             fg_hp_window,
-        };
-        entry.update_pos(tile_pos.tile_to_pixel());
-        entry
+        })
     }
 }
 
@@ -483,10 +390,10 @@ enum PokeAction {
 impl PokeAction {
     fn name(self) -> &'static Pkstr {
         match self {
-            PokeAction::Summary => pkstr!(b"Summary"),
-            PokeAction::Switch => pkstr!(b"Switch"),
-            PokeAction::GiveItem => pkstr!(b"Give Item"),
-            PokeAction::TakeItem => pkstr!(b"Take Item"),
+            PokeAction::Summary => pkstr!("Summary"),
+            PokeAction::Switch => pkstr!("Switch"),
+            PokeAction::GiveItem => pkstr!("Give Item"),
+            PokeAction::TakeItem => pkstr!("Take Item"),
         }
     }
 }
@@ -578,7 +485,7 @@ impl Menu<'_> {
                 AddBagItem(item as u16, 1);
             }
             entry.poke.set_item(0);
-            entry.item_sprite = None;
+            entry.sprites.remove(&EntrySprite::Item);
         }
     }
 
@@ -723,52 +630,54 @@ impl Menu<'_> {
 }
 
 struct Resources {
-    tileset: AllocBuf<TileBitmap4bpp>,
-    bg_map: AllocBuf<Tile4bpp>,
-    mon_slot_map: AllocBuf<Tile4bpp>,
+    tileset: Cached<TileBitmap4bpp>,
+    mon_slot_map: Cached<Tile4bpp>,
     bg_palettes: [BgPalette; 6],
 
-    hp_bar_map: AllocBuf<TilePlain>,
+    hp_bar_map: Cached<TilePlain>,
 
-    tera_sheet: SpriteSheet<AllocBuf<TileBitmap4bpp>>,
-    status_sheet: SpriteSheet<AllocBuf<TileBitmap4bpp>>,
+    tera_sheet: SpriteSheet<Cached<TileBitmap4bpp>>,
+    status_sheet: SpriteSheet<Cached<TileBitmap4bpp>>,
     status_pal: ObjPalette,
 }
 
-async fn load_resources() -> Resources {
-    let bg_palettes = load_bg_palettes(0, &PAL.load().get());
-    sleep(1).await;
+const LOAD_BYTES_PER_FRAME: usize = 0x800;
 
-    let tileset = TILESET.load();
+async fn load_resources() -> Result<Resources, OutOfMemory> {
+    let bg_palettes = load_bg_palettes(0, &PAL.try_load_cached()?.get());
     sleep(1).await;
 
-    let bg_map = SCROLL_BG_MAP.load();
-
-    sleep(1).await;
+    let tileset = TILESET
+        .try_load_cached_incremental(LOAD_BYTES_PER_FRAME)
+        .await?;
 
-    let mon_slot_map = MON_BG_MAP.load();
+    let mon_slot_map = MON_BG_MAP.try_load_cached()?;
     sleep(1).await;
 
-    let hp_bar_map = HP_MAP.load();
+    let hp_bar_map = HP_MAP.try_load_cached()?;
 
-    let tera_sheet = SpriteSheet::load(TERA_SPRITE.load(), 15000, SPRITE_SIZE_16x16 as u8);
-    sleep(1).await;
+    let tera_tiles = TERA_SPRITE
+        .try_load_cached_incremental(LOAD_BYTES_PER_FRAME)
+        .await?;
+    let tera_sheet = SpriteSheet::try_load(tera_tiles, 15000, SPRITE_SIZE_16x16 as u8)?;
 
-    let status_sheet = SpriteSheet::load(STATUS_SHEET.load(), 15001, SPRITE_SIZE_32x8 as u8);
-    let status_pal = load_obj_palette(12, &STATUS_PAL.load().get());
+    let status_tiles = STATUS_SHEET
+        .try_load_cached_incremental(LOAD_BYTES_PER_FRAME)
+        .await?;
+    let status_sheet = SpriteSheet::try_load(status_tiles, 15001, SPRITE_SIZE_32x8 as u8)?;
+    let status_pal = load_obj_palette(12, &STATUS_PAL.try_load_cached()?.get());
 
-    load_type_palettes();
+    load_type_palettes()?;
 
-    Resources {
+    Ok(Resources {
         tileset,
-        bg_map,
         mon_slot_map,
         bg_palettes,
         hp_bar_map,
         tera_sheet,
         status_sheet,
         status_pal,
-    }
+    })
 }
 
 async fn party_screen(
@@ -777,7 +686,7 @@ async fn party_screen(
     pokemons: *mut pokeemerald::Pokemon,
     count: usize,
     index: u8,
-) {
+) -> Result<(), OutOfMemory> {
     clear_ui().await;
 
     set_gpu_registers(&[
@@ -790,12 +699,9 @@ async fn party_screen(
         (REG_OFFSET_BLDY, &[]),
     ]);
 
-    let resources = load_resources().await;
+    let resources = load_resources().await?;
 
-    let bg_map = Tilemap {
-        map: 0,
-        buffer: &resources.bg_map,
-    };
+    let bg_map = Tilemap::load(0, &SCROLL_BG_MAP);
     let tileset = Tileset {
         char_base: 1,
         offset: 0,
@@ -807,20 +713,24 @@ async fn party_screen(
     let scroll_bg = scroll_bg.handle();
     scroll_bg.show();
 
-    let empty_tilemap = AllocBuf::new(vec![0u8; resources.bg_map.size_bytes()].into_boxed_slice());
+    // Tilemap buffers edited by the screen, released together on exit
+    let screen_size = ScreenSize::Size256x256;
+    let arena = Arena::new(2 * screen_size.tilemap_bytes())?;
+
     let empty_tilemap = Tilemap {
         map: 2,
-        buffer: empty_tilemap,
+        size: screen_size,
+        buffer: arena.alloc_buf(screen_size.tilemap_bytes())?,
     };
     let fixed_bg = Background::load(BackgroundIndex::Background2, 2, tileset, empty_tilemap).await;
     let fixed_bg = fixed_bg.handle();
     fixed_bg.set_pos(0, 0);
     fixed_bg.show();
 
-    let buffer_fg = AllocBuf::new(vec![0u8; resources.bg_map.size_bytes()].into_boxed_slice());
     let empty_tilemap = Tilemap {
         map: 4,
-        buffer: &buffer_fg,
+        size: screen_size,
+        buffer: arena.alloc_buf(screen_size.tilemap_bytes())?,
     };
     let fg = Background::load(BackgroundIndex::Background1, 1, tileset, empty_tilemap).await;
     let fg = fg.handle();
@@ -831,7 +741,7 @@ async fn party_screen(
     let mut entries: ArrayVec<Entry, 6> = ArrayVec::new();
     for i in 0..min(6, count) {
         let poke = unsafe { Pokemon::from_ptr_and_index(pokemons, i) };
-        entries.push(Entry::create(poke, &resources, fixed_bg, fg, i as u8).await);
+        entries.push(Entry::create(poke, &resources, fixed_bg, fg, i as u8).await?);
     }
 
     for entry in entries.iter() {
@@ -863,6 +773,7 @@ async fn party_screen(
     menu.main_loop().await;
     fade_palette(PaletteMask::ALL, 0, 0, 16, 0).await;
     (menu.exit_callback)();
+    Ok(())
 }
 
 async fn clear_ui() {
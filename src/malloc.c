@@ -196,14 +196,27 @@ void InitHeap(void *heapStart, u32 heapSize)
     PutFirstMemBlockHeader(heapStart, heapSize);
 }
 
+// Frees the resources the Rust side keeps cached, returning how many bytes it freed
+extern u32 FlushResourceCache(void);
+
 void *Alloc_(u32 size, const char *location)
 {
-    return AllocInternal(sHeapStart, size, location);
+    void *mem = AllocInternal(sHeapStart, size, location);
+
+    if (mem == NULL && FlushResourceCache() != 0)
+        mem = AllocInternal(sHeapStart, size, location);
+
+    return mem;
 }
 
 void *AllocZeroed_(u32 size, const char *location)
 {
-    return AllocZeroedInternal(sHeapStart, size, location);
+    void *mem = AllocZeroedInternal(sHeapStart, size, location);
+
+    if (mem == NULL && FlushResourceCache() != 0)
+        mem = AllocZeroedInternal(sHeapStart, size, location);
+
+    return mem;
 }
 
 void Free(void *pointer)